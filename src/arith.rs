@@ -0,0 +1,859 @@
+use std::vec::Vec;
+
+use {Primes, Factors, FactorError};
+
+impl Primes {
+    /// Compute Euler's totient function φ(*n*), the number of
+    /// integers between 1 and `n` that are coprime to `n`.
+    ///
+    /// This is computed from the factorisation of `n` as *n* ∏ (1 -
+    /// 1/*p*) over the primes *p* dividing `n`, using `n / p * (p -
+    /// 1)` for each prime so that no intermediate value is larger
+    /// than `n`. Returns the same `Err` as `factor` if `n` cannot be
+    /// fully factored (including for `n == 0`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.totient(1), Ok(1));
+    /// assert_eq!(sieve.totient(12), Ok(4));
+    /// assert_eq!(sieve.totient(97), Ok(96));
+    /// ```
+    pub fn totient(&self, n: usize) -> Result<usize, FactorError> {
+        match n {
+            0 => return Err(FactorError::Zero),
+            1 => return Ok(1),
+            _ => {}
+        }
+
+        let factors = self.factor(n)?;
+        Ok(factors.iter().fold(n, |phi, &(p, _)| phi / p * (p - 1)))
+    }
+
+    /// Compute the Carmichael function λ(*n*), the smallest positive
+    /// *m* with *a*<sup>*m*</sup> ≡ 1 (mod *n*) for every *a* coprime
+    /// to `n`.
+    ///
+    /// This is computed from the factorisation of `n`, as the least
+    /// common multiple of λ(*p*<sup>*k*</sup>) over the prime powers
+    /// exactly dividing `n`, where λ(*p*<sup>*k*</sup>) =
+    /// φ(*p*<sup>*k*</sup>) except that λ(2<sup>*k*</sup>) =
+    /// 2<sup>*k* - 2</sup> for *k* ≥ 3. Since λ(*n*) divides φ(*n*),
+    /// nothing can overflow. Returns the same `Err` as `factor` if `n`
+    /// cannot be fully factored (including for `n == 0`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.carmichael_lambda(8), Ok(2));
+    /// assert_eq!(sieve.carmichael_lambda(15), Ok(4));
+    /// // a Carmichael number, so λ(561) divides 560.
+    /// assert_eq!(sieve.carmichael_lambda(561), Ok(80));
+    /// ```
+    pub fn carmichael_lambda(&self, n: usize) -> Result<usize, FactorError> {
+        let factors = self.factor(n)?;
+        Ok(factors.iter().fold(1, |lambda, &(p, k)| {
+            let power = if p == 2 && k >= 3 {
+                1 << (k - 2)
+            } else {
+                // φ(p^k) = p^(k - 1) (p - 1)
+                (1..k).fold(p - 1, |x, _| x * p)
+            };
+            ::gcd::lcm(lambda as u64, power as u64).unwrap() as usize
+        }))
+    }
+
+    /// Compute the radical rad(*n*), the product of the distinct
+    /// primes dividing `n`.
+    ///
+    /// This is the largest squarefree divisor of `n`, so it is at
+    /// most `n` and can't overflow. Returns the same `Err` as
+    /// `factor` if `n` cannot be fully factored (including for `n ==
+    /// 0`), and the radical of the part that was factored is then
+    /// `partial.radical()` (see `Factors::radical`). Use
+    /// `radical_table` to compute the radicals of many numbers at once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.radical(1), Ok(1));
+    /// assert_eq!(sieve.radical(12), Ok(6));
+    /// assert_eq!(sieve.radical(97), Ok(97));
+    /// ```
+    pub fn radical(&self, n: usize) -> Result<usize, FactorError> {
+        let factors = self.factor(n)?;
+        Ok(factors.radical().expect("the radical of n is at most n"))
+    }
+
+    /// Compute the Möbius function μ(*n*).
+    ///
+    /// This is 0 if `n` is divisible by the square of a prime, and
+    /// otherwise 1 or -1 if `n` has an even or odd number
+    /// (respectively) of distinct prime factors.
+    ///
+    /// The factorisation of `n` stops as soon as a repeated prime
+    /// factor is found, so this can succeed even when `factor` would
+    /// fail. Otherwise, returns the same `Err` as `factor` if `n`
+    /// cannot be fully factored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.mobius(1), Ok(1));
+    /// assert_eq!(sieve.mobius(6), Ok(1));
+    /// assert_eq!(sieve.mobius(12), Ok(0));
+    /// assert_eq!(sieve.mobius(30), Ok(-1));
+    /// ```
+    pub fn mobius(&self, n: usize) -> Result<i8, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+
+        let mut partial = vec![];
+        let mut squarefree = true;
+        let result = self.factor_with(n, |p, k| {
+            partial.push((p, k));
+            squarefree = k == 1;
+            squarefree
+        });
+
+        match result {
+            Err(leftover) => Err(FactorError::LargeCofactor { leftover: leftover,
+                                                              partial: partial.into() }),
+            Ok(()) if !squarefree => Ok(0),
+            Ok(()) if partial.len() % 2 == 0 => Ok(1),
+            Ok(()) => Ok(-1),
+        }
+    }
+
+    /// Check if `n` is squarefree, that is, not divisible by the square
+    /// of any prime.
+    ///
+    /// Trial division stops as soon as a prime divides `n` twice, so
+    /// non-squarefree numbers are usually rejected quickly. If the
+    /// sieve runs out, the leftover only has prime factors larger than
+    /// `self.upper_bound()`, so if it is below the cube of that, it is
+    /// a prime, the product of two distinct primes, or the square of a
+    /// prime, and only the last is a perfect square. Otherwise, this
+    /// returns `Err(FactorError::LargeCofactor { .. })` like `factor`,
+    /// and `n == 0` gives `Err(FactorError::Zero)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(30);
+    ///
+    /// assert_eq!(sieve.is_squarefree(1), Ok(true));
+    /// assert_eq!(sieve.is_squarefree(30), Ok(true));
+    /// assert_eq!(sieve.is_squarefree(12), Ok(false));
+    /// // 31^2 and 31 * 37 are too large to `factor`, but can be decided.
+    /// assert_eq!(sieve.is_squarefree(31 * 31), Ok(false));
+    /// assert_eq!(sieve.is_squarefree(31 * 37), Ok(true));
+    /// ```
+    pub fn is_squarefree(&self, n: usize) -> Result<bool, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+
+        let mut squarefree = true;
+        let result = self.factor_with(n, |_, k| {
+            squarefree = k == 1;
+            squarefree
+        });
+
+        match result {
+            Ok(()) => Ok(squarefree),
+            Err(leftover) => {
+                let b = self.upper_bound() as u128;
+                if (leftover as u128) < b * b * b {
+                    let root = ::is_prime::isqrt(leftover as u64) as usize;
+                    Ok(root * root != leftover)
+                } else {
+                    // only allocate for the partial factorisation when
+                    // it's needed, which is rare.
+                    Err(self.factor(n).expect_err("factor_with already failed"))
+                }
+            }
+        }
+    }
+
+    /// Count the divisors of `n` (including 1 and `n`), that is,
+    /// compute *d*(*n*) = ∏ (*k* + 1) over the prime powers
+    /// *p*<sup>*k*</sup> dividing `n`.
+    ///
+    /// Returns the same `Err` as `factor` if `n` cannot be fully
+    /// factored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.divisor_count(1), Ok(1));
+    /// assert_eq!(sieve.divisor_count(12), Ok(6));
+    /// ```
+    pub fn divisor_count(&self, n: usize) -> Result<usize, FactorError> {
+        let factors = self.factor(n)?;
+        Ok(factors.iter().fold(1, |d, &(_, k)| d * (k + 1)))
+    }
+
+    /// Compute the sum of the divisors of `n` (including 1 and `n`),
+    /// that is, σ(*n*) = ∏ (*p*<sup>*k* + 1</sup> - 1)/(*p* - 1) over
+    /// the prime powers *p*<sup>*k*</sup> dividing `n`.
+    ///
+    /// Returns `Ok(None)` if the sum overflows a `usize`, and the same
+    /// `Err` as `factor` if `n` cannot be fully factored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.divisor_sum(1), Ok(Some(1)));
+    /// // 6 and 28 are perfect.
+    /// assert_eq!(sieve.divisor_sum(6), Ok(Some(12)));
+    /// assert_eq!(sieve.divisor_sum(28), Ok(Some(56)));
+    /// ```
+    pub fn divisor_sum(&self, n: usize) -> Result<Option<usize>, FactorError> {
+        // 1 + p + ... + p^k, avoiding computing p^(k + 1) which may
+        // overflow even if the sum doesn't.
+        fn geometric_sum(p: usize, k: usize) -> Option<usize> {
+            let mut sum = 1usize;
+            let mut power = 1usize;
+            for _ in 0..k {
+                power = power.checked_mul(p)?;
+                sum = sum.checked_add(power)?;
+            }
+            Some(sum)
+        }
+
+        let factors = self.factor(n)?;
+        Ok(factors.iter().try_fold(1usize, |sigma, &(p, k)| {
+            geometric_sum(p, k).and_then(|s| sigma.checked_mul(s))
+        }))
+    }
+
+    /// Iterate over the divisors of `n` (including 1 and `n`).
+    ///
+    /// These are generated directly from the factorisation of `n`,
+    /// as every product of prime powers *p*<sup>*j*</sup> with 0 ≤
+    /// *j* ≤ *k* for each *p*<sup>*k*</sup> exactly dividing `n`,
+    /// without storing them all. Returns the same `Err` as `factor`
+    /// if `n` cannot be fully factored.
+    ///
+    /// The divisors are yielded in mixed-radix order: the exponent of
+    /// the smallest prime varies fastest, so for 12 = 2<sup>2</sup>
+    /// × 3 the order is 1, 2, 4, 3, 6, 12. Collect and sort them if
+    /// increasing order is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// let divisors = sieve.divisors(12).unwrap();
+    /// assert_eq!(divisors.len(), 6);
+    /// assert_eq!(divisors.collect::<Vec<_>>(), [1, 2, 4, 3, 6, 12]);
+    ///
+    /// let mut sorted = sieve.divisors(60).unwrap().collect::<Vec<_>>();
+    /// sorted.sort();
+    /// assert_eq!(sorted, [1, 2, 3, 4, 5, 6, 10, 12, 15, 20, 30, 60]);
+    /// ```
+    pub fn divisors(&self, n: usize) -> Result<Divisors, FactorError> {
+        let factors = self.factor(n)?;
+
+        let count = factors.iter().fold(1, |d, &(_, k)| d * (k + 1));
+        Ok(Divisors {
+            exponents: vec![0; factors.len()],
+            factors: factors,
+            current: 1,
+            remaining: count,
+        })
+    }
+
+    /// Compute the primorial of `n`, that is, the product of the
+    /// first `n` primes, or `None` if that overflows a `usize`, or if
+    /// this sieve stores fewer than `n` primes.
+    ///
+    /// The product of no primes is 1. The multiplication stops as
+    /// soon as it would overflow, which is after at most 15 primes
+    /// for a 64-bit `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.primorial(0), Some(1));
+    /// assert_eq!(sieve.primorial(3), Some(2 * 3 * 5));
+    /// assert_eq!(sieve.primorial(10), Some(6_469_693_230));
+    /// // there are 25 primes below 100.
+    /// assert_eq!(sieve.primorial(26), None);
+    /// ```
+    pub fn primorial(&self, n: usize) -> Option<usize> {
+        if n > self.count_primes() { return None }
+        self.primes().take(n).try_fold(1usize, |acc, p| acc.checked_mul(p))
+    }
+
+    /// Compute the sum of all the primes stored in this sieve, or
+    /// `None` if that overflows a `usize`.
+    ///
+    /// For a 64-bit `usize`, this only overflows for sieves larger
+    /// than about 3 * 10<sup>10</sup>.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(2_000_000);
+    /// assert_eq!(sieve.sum_of_primes(), Some(142_913_828_922));
+    /// ```
+    pub fn sum_of_primes(&self) -> Option<usize> {
+        self.primes().try_fold(0usize, |acc, p| acc.checked_add(p))
+    }
+
+    /// Compute the sum of the primes less than `n`, or `None` if that
+    /// overflows a `usize`, or if `n` is larger than
+    /// `self.sieve_limit()` (so that some of the primes aren't
+    /// stored).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.sum_of_primes_below(10), Some(2 + 3 + 5 + 7));
+    /// assert_eq!(sieve.sum_of_primes_below(100), sieve.sum_of_primes());
+    /// assert_eq!(sieve.sum_of_primes_below(1000), None);
+    /// ```
+    pub fn sum_of_primes_below(&self, n: usize) -> Option<usize> {
+        if n > self.sieve_limit() { return None }
+        self.primes_in_range(0, n).try_fold(0usize, |acc, p| acc.checked_add(p))
+    }
+}
+
+/// Compute Euler's totient function φ(*n*) for every `n` less than
+/// `limit`, so that `table[n] == φ(n)` (with `table[0] == 0`).
+///
+/// This sieves all the values at once, in place, storing one `usize`
+/// per entry, which is much faster than calling `Primes::totient` on
+/// each number.
+///
+/// # Examples
+///
+/// ```rust
+/// let table = slow_primes::totient_table(13);
+/// assert_eq!(table, [0, 1, 1, 2, 2, 4, 2, 6, 4, 6, 4, 10, 4]);
+/// ```
+pub fn totient_table(limit: usize) -> Vec<usize> {
+    let mut table = (0..limit).collect::<Vec<_>>();
+    for p in 2..limit {
+        // still untouched, so p has no smaller prime factor.
+        if table[p] == p {
+            let mut m = p;
+            while m < limit {
+                table[m] -= table[m] / p;
+                m += p;
+            }
+        }
+    }
+    table
+}
+
+/// Compute the radical rad(*n*) for every `n` less than `limit`, so
+/// that `table[n] == rad(n)` (with `table[0] == 0`).
+///
+/// Like `totient_table`, this sieves all the values at once, which is
+/// much faster than calling `Primes::radical` on each number.
+///
+/// # Examples
+///
+/// ```rust
+/// let table = slow_primes::radical_table(13);
+/// assert_eq!(table, [0, 1, 2, 3, 2, 5, 6, 7, 2, 3, 10, 11, 6]);
+/// ```
+pub fn radical_table(limit: usize) -> Vec<usize> {
+    let mut table = vec![1; limit];
+    if limit > 0 { table[0] = 0 }
+    for p in 2..limit {
+        // still untouched, so p has no smaller prime factor.
+        if table[p] == 1 {
+            let mut m = p;
+            while m < limit {
+                table[m] *= p;
+                m += p;
+            }
+        }
+    }
+    table
+}
+
+/// Iterator over the divisors of a number, created by
+/// `Primes::divisors`.
+#[derive(Clone, Debug)]
+pub struct Divisors {
+    factors: Factors,
+    // the exponent of each prime in `current`.
+    exponents: Vec<usize>,
+    current: usize,
+    remaining: usize,
+}
+
+impl Iterator for Divisors {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None
+        }
+        self.remaining -= 1;
+        let ret = self.current;
+
+        if self.remaining > 0 {
+            // increment the exponents, as a mixed-radix number.
+            for (e, &(p, k)) in self.exponents.iter_mut().zip(self.factors.iter()) {
+                if *e < k {
+                    *e += 1;
+                    self.current *= p;
+                    break
+                }
+                self.current /= p.pow(k as u32);
+                *e = 0;
+            }
+        }
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Divisors {}
+
+#[cfg(test)]
+mod tests {
+    use {Primes, FactorError};
+    use super::{totient_table, radical_table};
+
+    #[test]
+    fn totient() {
+        let primes = Primes::sieve(1000);
+
+        let tests = [
+            (1, 1),
+            (2, 1),
+            (3, 2),
+            (4, 2),
+            (5, 4),
+            (6, 2),
+            (7, 6),
+            (8, 4),
+            (9, 6),
+            (10, 4),
+            (12, 4),
+            (36, 12),
+            (97, 96),
+            (1000, 400),
+            (7561, 7560),
+            ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.totient(n), Ok(expected));
+        }
+
+        // brute force
+        fn gcd(a: usize, b: usize) -> usize { if b == 0 { a } else { gcd(b, a % b) } }
+        for n in 1..1000 {
+            let phi = (1..n + 1).filter(|&i| gcd(i, n) == 1).count();
+            assert_eq!(primes.totient(n), Ok(phi));
+        }
+
+        // multiplicative on coprime pairs
+        for m in 1..100 {
+            for n in 1..100 {
+                if gcd(m, n) == 1 {
+                    assert_eq!(primes.totient(m * n),
+                               Ok(primes.totient(m).unwrap() * primes.totient(n).unwrap()));
+                }
+            }
+        }
+
+        // the sum of φ(d) over the divisors d of n is n
+        for n in 1..10_000 {
+            let sum = primes.divisors(n).unwrap()
+                .map(|d| primes.totient(d).unwrap())
+                .sum::<usize>();
+            assert_eq!(sum, n);
+        }
+
+        // nothing overflows near the top of the range.
+        let big = 65521 * 65521 * 65521 * 65519;
+        assert_eq!(Primes::sieve(1 << 16).totient(big), Ok(65521 * 65521 * 65520 * 65518));
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.totient(0), Err(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.totient(2 * 31 * 31), Err(large));
+    }
+
+    #[test]
+    fn carmichael_lambda() {
+        let primes = Primes::sieve(1000);
+
+        let tests = [(1, 1), (2, 1), (4, 2), (8, 2), (16, 4), (1024, 256), (15, 4),
+                     (561, 80), (97, 96), (97 * 97, 96 * 97), (720, 12), (7561, 7560)];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.carmichael_lambda(n), Ok(expected), "{}", n);
+        }
+
+        fn gcd(a: usize, b: usize) -> usize { if b == 0 { a } else { gcd(b, a % b) } }
+        fn pow_mod(a: usize, e: usize, n: usize) -> usize {
+            (0..e).fold(1 % n, |x, _| x * a % n)
+        }
+        for n in 1..10_000 {
+            let lambda = primes.carmichael_lambda(n).unwrap();
+            assert_eq!(primes.totient(n).unwrap() % lambda, 0, "{}", n);
+            for &a in [2, 3, 5, 7, 10, 11, 123].iter() {
+                if gcd(a, n) == 1 {
+                    assert_eq!(pow_mod(a, lambda, n), 1 % n, "{} {}", a, n);
+                }
+            }
+        }
+
+        // λ(n) is the largest multiplicative order modulo n.
+        for n in 2..300 {
+            let max_order = (1..n).filter(|&a| gcd(a, n) == 1)
+                .map(|a| (1..n + 1).find(|&m| pow_mod(a, m, n) == 1).unwrap())
+                .max().unwrap();
+            assert_eq!(primes.carmichael_lambda(n), Ok(max_order), "{}", n);
+        }
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.carmichael_lambda(0), Err(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.carmichael_lambda(2 * 31 * 31), Err(large));
+    }
+
+    #[test]
+    fn radical() {
+        let primes = Primes::sieve(1000);
+        let table = radical_table(100_000);
+
+        let tests = [(1, 1), (2, 2), (4, 2), (12, 6), (72, 6), (97, 97), (1000, 10),
+                     (2 * 3 * 5 * 7 * 11 * 13, 2 * 3 * 5 * 7 * 11 * 13), (7561 * 4, 7561 * 2)];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.radical(n), Ok(expected), "{}", n);
+        }
+
+        for n in 1..10_000 {
+            let rad = primes.radical(n).unwrap();
+            assert_eq!(n % rad, 0);
+            // the largest squarefree divisor
+            let brute = primes.divisors(n).unwrap()
+                .filter(|&d| primes.mobius(d).unwrap() != 0)
+                .max().unwrap();
+            assert_eq!(rad, brute, "{}", n);
+        }
+
+        for (n, &rad) in table.iter().enumerate().skip(1) {
+            assert_eq!(primes.radical(n), Ok(rad), "{}", n);
+        }
+        // multiplicative on coprime arguments
+        for a in 1..300 {
+            for b in 1..300 {
+                if ::gcd::gcd(a as u64, b as u64) == 1 {
+                    assert_eq!(table[a * b], table[a] * table[b], "{} {}", a, b);
+                }
+            }
+        }
+        assert!(radical_table(0).is_empty());
+        assert_eq!(radical_table(1), [0]);
+        assert_eq!(radical_table(2), [0, 1]);
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.radical(0), Err(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.radical(2 * 31 * 31), Err(large));
+        match small.radical(8 * 27 * 31 * 37) {
+            Err(FactorError::LargeCofactor { leftover, partial }) => {
+                assert_eq!(leftover, 31 * 37);
+                assert_eq!(partial.radical(), Some(6));
+            }
+            r => panic!("{:?}", r)
+        }
+    }
+
+    #[test]
+    fn primorial() {
+        let sieve = Primes::sieve(1000);
+        let expected = [1, 2, 6, 30, 210, 2310, 30030, 510510, 9699690, 223092870];
+        for (n, &p) in expected.iter().enumerate() {
+            assert_eq!(sieve.primorial(n), Some(p));
+        }
+
+        // 47# is the largest that fits in 64 bits.
+        assert_eq!(sieve.primorial(15), Some(614_889_782_588_491_410));
+        assert_eq!(sieve.primorial(16), None);
+        assert_eq!(sieve.primorial(sieve.count_primes()), None);
+        assert_eq!(sieve.primorial(usize::MAX), None);
+
+        let small = Primes::sieve(10);
+        assert_eq!(small.primorial(4), Some(210));
+        assert_eq!(small.primorial(5), None);
+        assert_eq!(Primes::sieve(0).primorial(0), Some(1));
+    }
+
+    #[test]
+    fn sum_of_primes() {
+        let sieve = Primes::sieve(10_000);
+        let tests = [(0, 0), (1, 0), (2, 0), (3, 2), (4, 5), (10, 17), (11, 17), (12, 28),
+                     (100, 1060), (1000, 76_127)];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(sieve.sum_of_primes_below(n), Some(expected), "{}", n);
+        }
+
+        let mut sum = 0;
+        for n in 0..sieve.sieve_limit() + 1 {
+            assert_eq!(sieve.sum_of_primes_below(n), Some(sum), "{}", n);
+            if sieve.is_prime(n) { sum += n }
+        }
+        assert_eq!(sieve.sum_of_primes(), Some(sum));
+        assert_eq!(sieve.sum_of_primes_below(sieve.sieve_limit() + 1), None);
+        assert_eq!(sieve.sum_of_primes_below(usize::MAX), None);
+
+        assert_eq!(Primes::sieve(0).sum_of_primes(), Some(17));
+    }
+
+    #[test]
+    fn totient_sieve() {
+        let primes = Primes::sieve(1000);
+        let table = totient_table(100_000);
+        assert_eq!(table.len(), 100_000);
+        assert_eq!(table[0], 0);
+        for (n, &phi) in table.iter().enumerate().skip(1) {
+            assert_eq!(Ok(phi), primes.totient(n), "{}", n);
+        }
+        for p in primes.primes() {
+            assert_eq!(table[p], p - 1);
+        }
+
+        assert!(totient_table(0).is_empty());
+        assert_eq!(totient_table(1), [0]);
+        assert_eq!(totient_table(2), [0, 1]);
+    }
+
+    #[test]
+    fn mobius() {
+        let primes = Primes::sieve(1000);
+
+        let tests = [
+            (1, 1),
+            (2, -1),
+            (3, -1),
+            (4, 0),
+            (5, -1),
+            (6, 1),
+            (7, -1),
+            (8, 0),
+            (9, 0),
+            (10, 1),
+            (30, -1),
+            (210, 1),
+            (7561, -1),
+            (2 * 7561, 1),
+            ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.mobius(n), Ok(expected));
+        }
+
+        for n in 1..10_000 {
+            let factors = primes.factor(n).unwrap();
+            let expected = if factors.iter().any(|&(_, k)| k > 1) {
+                0
+            } else if factors.len() % 2 == 0 {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(primes.mobius(n), Ok(expected));
+        }
+
+        // brute force, without the sieve
+        fn naive_mobius(mut n: usize) -> i8 {
+            let mut mu = 1;
+            let mut d = 2;
+            while n > 1 {
+                if n % d == 0 {
+                    n /= d;
+                    if n % d == 0 { return 0 }
+                    mu = -mu;
+                }
+                d += 1;
+            }
+            mu
+        }
+        for n in 1..10_000 {
+            assert_eq!(primes.mobius(n), Ok(naive_mobius(n)), "{}", n);
+        }
+
+        // the sum of μ(d) over the divisors d of n is 0, except for n = 1.
+        for n in 1..10_000 {
+            let sum = primes.divisors(n).unwrap()
+                .map(|d| primes.mobius(d).unwrap() as i32)
+                .sum::<i32>();
+            assert_eq!(sum, if n == 1 { 1 } else { 0 }, "{}", n);
+        }
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.mobius(0), Err(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.mobius(2 * 31 * 31), Err(large));
+        // doesn't need to look at the large factors at all.
+        assert_eq!(small.mobius(4 * 31 * 31), Ok(0));
+    }
+
+    #[test]
+    fn squarefree() {
+        let primes = Primes::sieve(1000);
+        assert_eq!(primes.is_squarefree(0), Err(FactorError::Zero));
+        assert_eq!(primes.is_squarefree(1), Ok(true));
+        for n in 1..100_000 {
+            assert_eq!(primes.is_squarefree(n), Ok(primes.mobius(n).unwrap() != 0), "{}", n);
+        }
+
+        // squares of primes just past the sieve, and their neighbours.
+        let (p, q) = (1009, 1013);
+        assert!(primes.factor(p * p).is_err());
+        assert_eq!(primes.is_squarefree(p * p), Ok(false));
+        assert_eq!(primes.is_squarefree(6 * p * p), Ok(false));
+        assert_eq!(primes.is_squarefree(p * q), Ok(true));
+        assert_eq!(primes.is_squarefree(6 * p * q), Ok(true));
+        assert_eq!(primes.is_squarefree(4 * p * q), Ok(false));
+        // the leftover could also be p^3, or p * q * r.
+        let large = FactorError::LargeCofactor { leftover: p * p * p, partial: vec![(2, 1)].into() };
+        assert_eq!(primes.is_squarefree(2 * p * p * p), Err(large));
+        assert!(primes.is_squarefree(p * q * 1019).is_err());
+        // the largest prime square below the cube of the bound.
+        let r = 31_573;
+        assert!(r * r < 999 * 999 * 999);
+        assert_eq!(primes.is_squarefree(r * r), Ok(false));
+        // the next prime, 31_583, takes the product past the cube.
+        assert!(primes.is_squarefree(r * 31_583).is_err());
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.is_squarefree(31 * 31), Ok(false));
+        assert_eq!(small.is_squarefree(31 * 37), Ok(true));
+        assert_eq!(small.is_squarefree(3 * 5 * 31 * 37), Ok(true));
+        assert!(small.is_squarefree(0xFFFF_FFFF_FFFF_FFFF).is_err());
+        // found before the large factor.
+        assert_eq!(small.is_squarefree(4 * 31 * 37 * 41), Ok(false));
+    }
+
+    #[test]
+    fn divisor_count_and_sum() {
+        let primes = Primes::sieve(1000);
+
+        let tests = [
+            (1, 1, 1),
+            (2, 2, 3),
+            (4, 3, 7),
+            (6, 4, 12),
+            (12, 6, 28),
+            (28, 6, 56),
+            (496, 10, 992),
+            (8128, 14, 16256),
+            (7561, 2, 7562),
+            ];
+        for &(n, count, sum) in tests.iter() {
+            assert_eq!(primes.divisor_count(n), Ok(count));
+            assert_eq!(primes.divisor_sum(n), Ok(Some(sum)));
+        }
+
+        for n in 1..1000 {
+            let divisors = (1..n + 1).filter(|&d| n % d == 0);
+            assert_eq!(primes.divisor_count(n), Ok(divisors.clone().count()));
+            assert_eq!(primes.divisor_sum(n), Ok(Some(divisors.sum())));
+        }
+
+        // brute force enumeration of the divisors, by marking every
+        // multiple of each d.
+        const LIMIT: usize = 10_000;
+        let mut counts = vec![0; LIMIT];
+        let mut sums = vec![0; LIMIT];
+        for d in 1..LIMIT {
+            for m in (d..LIMIT).step_by(d) {
+                counts[m] += 1;
+                sums[m] += d;
+            }
+        }
+        for n in 1..LIMIT {
+            assert_eq!(primes.divisor_count(n), Ok(counts[n]), "{}", n);
+            assert_eq!(primes.divisor_sum(n), Ok(Some(sums[n])), "{}", n);
+        }
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.divisor_count(0), Err(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.divisor_sum(2 * 31 * 31), Err(large));
+
+        let bits = ::std::mem::size_of::<usize>() * 8;
+        // σ(2^(bits - 1)) = 2^bits - 1 just fits...
+        assert_eq!(small.divisor_sum(1 << (bits - 1)), Ok(Some(!0)));
+        // ... but this doesn't.
+        assert_eq!(small.divisor_sum(3 << (bits - 2)), Ok(None));
+    }
+
+    #[test]
+    fn divisors() {
+        let primes = Primes::sieve(1000);
+        let sorted = |n| {
+            let mut v = primes.divisors(n).unwrap().collect::<Vec<_>>();
+            v.sort();
+            v
+        };
+
+        assert_eq!(primes.divisors(1).unwrap().collect::<Vec<_>>(), [1]);
+        assert_eq!(primes.divisors(97).unwrap().collect::<Vec<_>>(), [1, 97]);
+        assert_eq!(primes.divisors(2 * 2 * 3 * 1009).unwrap().collect::<Vec<_>>(),
+                   [1, 2, 4, 3, 6, 12, 1009, 2018, 4036, 3027, 6054, 12108]);
+
+        for n in 1..2000 {
+            let expected = (1..n + 1).filter(|d| n % d == 0).collect::<Vec<_>>();
+            assert_eq!(sorted(n), expected);
+        }
+
+        for n in 1..10_000 {
+            let divisors = primes.divisors(n).unwrap();
+            let count = primes.divisor_count(n).unwrap();
+            assert_eq!(divisors.len(), count);
+
+            // d and n / d are both divisors
+            let list = divisors.collect::<Vec<_>>();
+            assert_eq!(list.len(), count);
+            for &d in list.iter() {
+                assert_eq!(n % d, 0);
+                assert!(list.contains(&(n / d)));
+            }
+            // and the sorted list pairs up from both ends.
+            let sorted = sorted(n);
+            for (a, b) in sorted.iter().zip(sorted.iter().rev()) {
+                assert_eq!(a * b, n);
+            }
+        }
+
+        // highly composite
+        let mut divisors = primes.divisors(720720).unwrap();
+        assert_eq!(divisors.size_hint(), (240, Some(240)));
+        divisors.next();
+        assert_eq!(divisors.len(), 239);
+        assert!(divisors.all(|d| 720720 % d == 0));
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.divisors(0).err(), Some(FactorError::Zero));
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.divisors(2 * 31 * 31).err(), Some(large));
+    }
+}
@@ -0,0 +1,341 @@
+use std::vec::Vec;
+
+use num_::{BigUint, Integer, One, ToPrimitive, Zero};
+
+use Primes;
+use is_prime::{is_prime_u64, XorShift};
+use tables;
+
+// the Miller-Rabin bases that are always tried, which are enough to
+// be sure for everything below DETERMINISTIC_LIMIT.
+const BASES: &'static [u32] = &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+const DETERMINISTIC_LIMIT: u128 = 3_317_044_064_679_887_385_961_981;
+
+impl Primes {
+    /// Factorise `n` into (prime, exponent) pairs, for `n` of any
+    /// size.
+    ///
+    /// This is the same trial division as `factor`, and so can fully
+    /// factorise every `n` below `self.upper_bound()^2`, along with
+    /// any others that only have one prime factor larger than the
+    /// sieve (which must be below `self.upper_bound()^2`). Otherwise,
+    /// returns `Err((leftover, partial))`, with the parts that would
+    /// be in `FactorError::LargeCofactor`, and `n == 0` gives
+    /// `Err((0, vec![]))`.
+    ///
+    /// Rather than dividing `n` by each prime, this finds the
+    /// remainder of `n` modulo the product of as many primes as fit
+    /// in a `u64`, and only divides `n` by the primes that divide
+    /// that.
+    ///
+    /// This requires the `bigint` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate num;
+    /// extern crate slow_primes;
+    ///
+    /// # #[cfg(feature = "bigint")]
+    /// fn main() {
+    ///     use num::BigUint;
+    ///
+    ///     let sieve = slow_primes::Primes::sieve(100);
+    ///
+    ///     let n = BigUint::from(2u32).pow(100) * 97u32;
+    ///     assert_eq!(sieve.factor_big(&n),
+    ///                Ok(vec![(BigUint::from(2u32), 100), (BigUint::from(97u32), 1)]));
+    ///
+    ///     // 2^89 - 1 is prime, but much too large for this sieve.
+    ///     let m89 = BigUint::from(2u32).pow(89) - 1u32;
+    ///     assert_eq!(sieve.factor_big(&(&m89 * 3u32)),
+    ///                Err((m89, vec![(BigUint::from(3u32), 1)])));
+    /// }
+    /// # #[cfg(not(feature = "bigint"))] fn main() {}
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn factor_big(&self, n: &BigUint) -> Result<Vec<(BigUint, u32)>, (BigUint, Vec<(BigUint, u32)>)> {
+        if n.is_zero() { return Err((BigUint::zero(), vec![])) }
+
+        let mut n = n.clone();
+        let mut ret = vec![];
+        let mut primes = self.primes().map(|p| p as u64).peekable();
+        let mut batch = vec![];
+        while primes.peek().is_some() && !n.is_one() {
+            // the next batch of primes, and their product.
+            batch.clear();
+            let mut product: u64 = 1;
+            while let Some(&p) = primes.peek() {
+                match product.checked_mul(p) {
+                    Some(x) => product = x,
+                    None => break
+                }
+                batch.push(p);
+                primes.next();
+            }
+
+            let rem = (&n % product).to_u64().unwrap();
+            for &p in batch.iter() {
+                if rem % p != 0 { continue }
+
+                let p = BigUint::from(p);
+                let mut count = 0;
+                loop {
+                    let (q, r) = n.div_rem(&p);
+                    if !r.is_zero() { break }
+                    n = q;
+                    count += 1;
+                }
+                ret.push((p, count));
+            }
+
+            // nothing up to the largest prime so far divides n, so it
+            // must be prime (or 1) if it's at most that squared.
+            let largest = BigUint::from(batch[batch.len() - 1]);
+            if &largest * &largest >= n {
+                break
+            }
+        }
+
+        if !n.is_one() {
+            let b = BigUint::from(self.upper_bound());
+            if &b * &b >= n {
+                ret.push((n, 1))
+            } else {
+                return Err((n, ret))
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Test if `n` is probably prime, for `n` of any size.
+///
+/// Numbers that fit in a `u64` are tested exactly, with
+/// `is_prime_u64`. Larger ones are trial divided by the primes below
+/// 1619, and then checked with the Miller-Rabin test, with the bases 2,
+/// 3, ..., 41 (the first 13 primes), and then `rounds` more
+/// pseudo-random bases. The fixed bases are enough to be certain for
+/// `n` below 3317044064679887385961981 (about 2<sup>81</sup>).
+/// Beyond that, a composite `n` is reported as prime with
+/// probability at most 4<sup>-`rounds`</sup>, and much less for
+/// typical `n`. The random bases are derived from `n` itself, so the
+/// answer for any given `n` is always the same.
+///
+/// This requires the `bigint` feature. It is fine for checking
+/// candidates (including ones the size of RSA primes), but is not a
+/// vetted implementation for real cryptography.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate num;
+/// extern crate slow_primes;
+///
+/// # #[cfg(feature = "bigint")]
+/// fn main() {
+///     use num::BigUint;
+///     use slow_primes::is_probable_prime_big;
+///
+///     let m127 = BigUint::from(2u32).pow(127) - 1u32;
+///     assert!(is_probable_prime_big(&m127, 10));
+///     assert!(!is_probable_prime_big(&(m127 + 2u32), 10));
+///
+///     assert!(is_probable_prime_big(&BigUint::from(97u32), 0));
+/// }
+/// # #[cfg(not(feature = "bigint"))] fn main() {}
+/// ```
+pub fn is_probable_prime_big(n: &BigUint, rounds: u32) -> bool {
+    if let Some(n) = n.to_u64() {
+        return is_prime_u64(n, None)
+    }
+    if n.is_even() { return false }
+
+    // n is larger than every small prime, so it can't be one of them.
+    let mut primes = (2..tables::SMALL_PRIME_PI.len())
+        .filter(|&k| tables::SMALL_PRIME_PI[k] != tables::SMALL_PRIME_PI[k - 1])
+        .map(|p| p as u64)
+        .peekable();
+    while primes.peek().is_some() {
+        let mut product: u64 = 1;
+        let mut batch = vec![];
+        while let Some(&p) = primes.peek() {
+            match product.checked_mul(p) {
+                Some(x) => product = x,
+                None => break
+            }
+            batch.push(p);
+            primes.next();
+        }
+        let rem = (n % product).to_u64().unwrap();
+        if batch.iter().any(|&p| rem % p == 0) {
+            return false
+        }
+    }
+
+    let n_minus_1 = n - 1u32;
+    let s = n_minus_1.trailing_zeros().unwrap();
+    let d = &n_minus_1 >> s;
+    let witness = |a: &BigUint| strong_probable_prime(n, &n_minus_1, &d, s, a);
+
+    if !BASES.iter().all(|&a| witness(&BigUint::from(a))) {
+        return false
+    }
+    let deterministic = match n.to_u128() {
+        Some(n) => n < DETERMINISTIC_LIMIT,
+        None => false
+    };
+    if rounds == 0 || deterministic {
+        return true
+    }
+
+    // random bases in 2..n - 1
+    let mut rng = XorShift::new(n.iter_u64_digits().next().unwrap());
+    let digits = n.bits() as usize / 32 + 1;
+    let range = n - 3u32;
+    (0..rounds).all(|_| {
+        let random = BigUint::new((0..digits).map(|_| rng.next() as u32).collect());
+        witness(&(random % &range + 2u32))
+    })
+}
+
+// The Miller-Rabin test for the base `a`, where `n - 1 = d * 2^s`,
+// with `d` odd.
+fn strong_probable_prime(n: &BigUint, n_minus_1: &BigUint, d: &BigUint, s: u64, a: &BigUint) -> bool {
+    let mut x = a.modpow(d, n);
+    if x.is_one() || x == *n_minus_1 { return true }
+
+    for _ in 1..s {
+        x = &x * &x % n;
+        if x == *n_minus_1 { return true }
+        // 1 has no square roots other than ±1 modulo a prime.
+        if x.is_one() { return false }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use num_::{BigUint, One};
+
+    use {Primes, FactorError};
+    use super::is_probable_prime_big;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn convert(factors: &[(usize, usize)]) -> Vec<(BigUint, u32)> {
+        factors.iter().map(|&(p, k)| (big(p as u64), k as u32)).collect()
+    }
+
+    #[test]
+    fn factor_small() {
+        let short = Primes::sieve(30);
+        let long = Primes::sieve(100_000);
+
+        let check = |n: usize| {
+            for primes in [&short, &long].iter() {
+                let expected = match primes.factor(n) {
+                    Ok(f) => Ok(convert(&f)),
+                    Err(FactorError::Zero) => Err((big(0), vec![])),
+                    Err(FactorError::LargeCofactor { leftover, partial }) => {
+                        Err((big(leftover as u64), convert(&partial)))
+                    }
+                };
+                assert_eq!(primes.factor_big(&big(n as u64)), expected, "{}", n);
+            }
+        };
+        for n in 0..20_000 {
+            check(n)
+        }
+        for n in (20_000..1_000_000_000).step_by(999_983) {
+            check(n)
+        }
+        check(999_999_937);
+        check(999_999_937 * 3);
+    }
+
+    #[test]
+    fn factor_large() {
+        let sieve = Primes::sieve(1_000_000);
+        let m89 = (BigUint::one() << 89usize) - 1u32;
+
+        let n = &m89 * 2u32 * 2u32 * 3u32 * 999_983u32;
+        assert_eq!(sieve.factor_big(&n),
+                   Err((m89.clone(), vec![(big(2), 2), (big(3), 1), (big(999_983), 1)])));
+        assert_eq!(sieve.factor_big(&m89), Err((m89.clone(), vec![])));
+
+        // everything is below the primes in the sieve.
+        let n = (BigUint::one() << 200usize) * big(3).pow(70) * big(999_983).pow(5);
+        assert_eq!(sieve.factor_big(&n), Ok(vec![(big(2), 200), (big(3), 70), (big(999_983), 5)]));
+
+        // the largest prime below the upper bound squared.
+        let p = big(999_997_999_981);
+        let n = &p * big(7).pow(30);
+        assert_eq!(sieve.factor_big(&n), Ok(vec![(big(7), 30), (p, 1)]));
+
+        // a 31 digit semiprime, with both factors too large.
+        let pq = big(1_000_000_000_000_037) * big(1_000_000_000_000_091);
+        assert_eq!(pq.to_string(), "1000000000000128000000000003367");
+        assert_eq!(sieve.factor_big(&(&pq * 5u32)), Err((pq.clone(), vec![(big(5), 1)])));
+        assert_eq!(sieve.factor_big(&pq), Err((pq, vec![])));
+
+        assert_eq!(sieve.factor_big(&BigUint::one()), Ok(vec![]));
+    }
+
+    #[test]
+    fn probable_prime_small() {
+        let sieve = Primes::sieve(1_000_000);
+        for n in 0..1_000_000 {
+            assert_eq!(is_probable_prime_big(&big(n as u64), 0), sieve.is_prime(n), "{}", n);
+        }
+        assert!(is_probable_prime_big(&big(18_446_744_073_709_551_557), 0));
+        // a strong pseudoprime to the bases 2, 3, ..., 23.
+        assert!(!is_probable_prime_big(&big(3_825_123_056_546_413_051), 0));
+    }
+
+    #[test]
+    fn probable_prime_large() {
+        let parse = |s: &str| s.parse::<BigUint>().unwrap();
+        let one = BigUint::one();
+
+        let primes = [
+            (&one << 89usize) - 1u32,
+            (&one << 107usize) - 1u32,
+            (&one << 127usize) - 1u32,
+            (&one << 521usize) - 1u32,
+            // the factors of RSA-100.
+            parse("37975227936943673922808872755445627854565536638199"),
+            parse("40094690950920881030683735292761468389214899724061"),
+            // the smallest 100 digit prime.
+            BigUint::from(10u32).pow(99) + 289u32,
+            // the smallest prime larger than a u64.
+            parse("18446744073709551629"),
+            ];
+        for p in primes.iter() {
+            assert!(is_probable_prime_big(p, 0), "{}", p);
+            assert!(is_probable_prime_big(p, 20), "{}", p);
+            assert!(!is_probable_prime_big(&(p * p), 20), "{}^2", p);
+            assert!(!is_probable_prime_big(&(p * 1609u32), 20), "{} * 1609", p);
+        }
+        assert!(!is_probable_prime_big(&(&primes[4] * &primes[5]), 20));
+
+        let composites = [
+            (&one << 67usize) - 1u32,
+            (&one << 128usize) + 1u32,
+            (&one << 200usize),
+            // a Carmichael number, (6k + 1)(12k + 1)(18k + 1).
+            parse("1296056805229926801774649"),
+            // strong pseudoprimes to the first 12 and 13 prime bases.
+            parse("318665857834031151167461"),
+            parse("3317044064679887385961981"),
+            // 1621 is just past the trial division.
+            parse("18446744073709551557") * 1621u32,
+            ];
+        for n in composites.iter() {
+            assert!(!is_probable_prime_big(n, 10), "{}", n);
+        }
+        assert!(!is_probable_prime_big(&parse("318665857834031151167461"), 0));
+    }
+}
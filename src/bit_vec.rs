@@ -0,0 +1,389 @@
+//! A minimal bit vector, providing the parts of the interface of the
+//! old `std::collections::BitVec` that the sieves use.
+//!
+//! The bits are stored in `u64` blocks, with bit `i` being bit `i %
+//! 64` of block `i / 64`, and any bits past the length are always
+//! zero.
+
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+use std::vec::Vec;
+
+/// The number of bits in each block.
+pub const BITS: usize = 64;
+
+static TRUE: bool = true;
+static FALSE: bool = false;
+
+fn blocks_for(bits: usize) -> usize {
+    (bits + BITS - 1) / BITS
+}
+
+/// A vector of bits.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    storage: Vec<u64>,
+    nbits: usize,
+}
+
+impl BitVec {
+    /// Create an empty `BitVec`.
+    pub fn new() -> BitVec {
+        BitVec { storage: Vec::new(), nbits: 0 }
+    }
+
+    /// Create an empty `BitVec` with space for at least `nbits`
+    /// bits.
+    pub fn with_capacity(nbits: usize) -> BitVec {
+        BitVec { storage: Vec::with_capacity(blocks_for(nbits)), nbits: 0 }
+    }
+
+    /// Create a `BitVec` of `nbits` bits, all set to `bit`.
+    pub fn from_elem(nbits: usize, bit: bool) -> BitVec {
+        let block = if bit { !0 } else { 0 };
+        let mut v = BitVec { storage: vec![block; blocks_for(nbits)], nbits: nbits };
+        v.fix_last_block();
+        v
+    }
+
+    /// Create a `BitVec` from bytes, with the first bit of each byte
+    /// being its most significant.
+    pub fn from_bytes(bytes: &[u8]) -> BitVec {
+        let mut storage = Vec::with_capacity(blocks_for(bytes.len() * 8));
+        for chunk in bytes.chunks(BITS / 8) {
+            let mut block = 0;
+            for (i, &b) in chunk.iter().enumerate() {
+                block |= (b.reverse_bits() as u64) << (8 * i);
+            }
+            storage.push(block);
+        }
+        BitVec { storage: storage, nbits: bytes.len() * 8 }
+    }
+
+    /// Create a `BitVec` of `nbits` bits stored in `blocks`, as
+    /// returned by `as_blocks`.
+    ///
+    /// # Panics
+    ///
+    /// If `blocks` is the wrong length for `nbits`, or has bits set
+    /// past the end.
+    pub fn from_blocks(blocks: Vec<u64>, nbits: usize) -> BitVec {
+        assert!(blocks.len() == blocks_for(nbits),
+                "BitVec::from_blocks: {} blocks can't store {} bits", blocks.len(), nbits);
+        let v = BitVec { storage: blocks, nbits: nbits };
+        assert!(nbits % BITS == 0 || v.storage[v.storage.len() - 1] >> (nbits % BITS) == 0,
+                "BitVec::from_blocks: bits set past the end");
+        v
+    }
+
+    /// The blocks storing the bits.
+    #[inline]
+    pub fn as_blocks(&self) -> &[u64] {
+        &self.storage
+    }
+
+    /// The number of bytes allocated for the blocks, including any
+    /// spare capacity.
+    pub fn heap_size(&self) -> usize {
+        self.storage.capacity() * mem::size_of::<u64>()
+    }
+
+    /// Pack the bits into bytes, with the first bit of each byte
+    /// being its most significant, and any trailing bits of the last
+    /// byte being zero.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len = (self.nbits + 7) / 8;
+        let mut ret = Vec::with_capacity(len);
+        'outer: for &block in self.storage.iter() {
+            for i in 0..BITS / 8 {
+                if ret.len() == len { break 'outer }
+                ret.push(((block >> (8 * i)) as u8).reverse_bits());
+            }
+        }
+        ret
+    }
+
+    /// The number of bits.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nbits
+    }
+
+    /// Return bit `i`, or `None` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<bool> {
+        if i < self.nbits {
+            Some(self.storage[i / BITS] & (1 << (i % BITS)) != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Return bit `i`, without checking that it is in bounds.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than `self.len()`.
+    #[inline]
+    pub unsafe fn get_unchecked(&self, i: usize) -> bool {
+        debug_assert!(i < self.nbits);
+        *self.storage.get_unchecked(i / BITS) & (1 << (i % BITS)) != 0
+    }
+
+    /// Set bit `i` to `x`.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of bounds.
+    #[inline]
+    pub fn set(&mut self, i: usize, x: bool) {
+        assert!(i < self.nbits, "BitVec::set: index {} out of bounds ({})", i, self.nbits);
+        let block = &mut self.storage[i / BITS];
+        let mask = 1 << (i % BITS);
+        if x { *block |= mask } else { *block &= !mask }
+    }
+
+    /// Set every bit.
+    pub fn set_all(&mut self) {
+        for block in self.storage.iter_mut() {
+            *block = !0;
+        }
+        self.fix_last_block();
+    }
+
+    /// Append a bit.
+    pub fn push(&mut self, x: bool) {
+        if self.nbits % BITS == 0 {
+            self.storage.push(0);
+        }
+        self.nbits += 1;
+        if x {
+            let i = self.nbits - 1;
+            self.storage[i / BITS] |= 1 << (i % BITS);
+        }
+    }
+
+    /// Append `n` copies of `x`.
+    pub fn grow(&mut self, n: usize, x: bool) {
+        let new_len = self.nbits + n;
+        if x {
+            // fill the rest of the current last block.
+            let extra = self.nbits % BITS;
+            if extra != 0 {
+                let last = self.storage.len() - 1;
+                self.storage[last] |= !0 << extra;
+            }
+        }
+        self.storage.resize(blocks_for(new_len), if x { !0 } else { 0 });
+        self.nbits = new_len;
+        self.fix_last_block();
+    }
+
+    /// Append all the bits of `other`.
+    ///
+    /// This works a block at a time, rather than bit by bit.
+    pub fn append(&mut self, other: &BitVec) {
+        let shift = self.nbits % BITS;
+        if shift == 0 {
+            self.storage.extend_from_slice(&other.storage);
+        } else {
+            self.storage.reserve(other.storage.len());
+            for &block in other.storage.iter() {
+                let last = self.storage.len() - 1;
+                self.storage[last] |= block << shift;
+                self.storage.push(block >> (BITS - shift));
+            }
+        }
+        self.nbits += other.nbits;
+        self.storage.truncate(blocks_for(self.nbits));
+    }
+
+    /// Shorten to `len` bits, if longer.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.nbits {
+            self.nbits = len;
+            self.storage.truncate(blocks_for(len));
+            self.fix_last_block();
+        }
+    }
+
+    /// Free any spare capacity.
+    pub fn shrink_to_fit(&mut self) {
+        self.storage.shrink_to_fit()
+    }
+
+    /// Iterator over the blocks storing the bits.
+    pub fn blocks<'a>(&'a self) -> Blocks<'a> {
+        Blocks { iter: self.storage.iter() }
+    }
+
+    // Clear the bits past the end.
+    fn fix_last_block(&mut self) {
+        let extra = self.nbits % BITS;
+        if extra != 0 {
+            let last = self.storage.len() - 1;
+            self.storage[last] &= (1 << extra) - 1;
+        }
+    }
+}
+
+impl Index<usize> for BitVec {
+    type Output = bool;
+
+    #[inline]
+    fn index(&self, i: usize) -> &bool {
+        match self.get(i) {
+            Some(true) => &TRUE,
+            Some(false) => &FALSE,
+            None => panic!("BitVec: index {} out of bounds ({})", i, self.nbits)
+        }
+    }
+}
+
+impl Extend<bool> for BitVec {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.storage.reserve(blocks_for(iter.size_hint().0));
+        for bit in iter {
+            self.push(bit)
+        }
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> BitVec {
+        let mut v = BitVec::new();
+        v.extend(iter);
+        v
+    }
+}
+
+/// Iterator over the `u64` blocks storing the bits of a `BitVec`.
+#[derive(Clone)]
+pub struct Blocks<'a> {
+    iter: slice::Iter<'a, u64>,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        self.iter.next().cloned()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    // so that `skip` is constant time.
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<u64> {
+        self.iter.nth(n).cloned()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Blocks<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<u64> {
+        self.iter.next_back().cloned()
+    }
+}
+
+impl<'a> ExactSizeIterator for Blocks<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::BitVec;
+
+    fn bits(v: &BitVec) -> Vec<bool> {
+        (0..v.len()).map(|i| v[i]).collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut v = BitVec::from_elem(134, true);
+        assert_eq!(v.len(), 134);
+        assert!(bits(&v).iter().all(|&b| b));
+        assert_eq!(v.blocks().collect::<Vec<_>>(), [!0, !0, (1 << 6) - 1]);
+        assert_eq!(v.blocks().skip(2).collect::<Vec<_>>(), [(1 << 6) - 1]);
+        assert_eq!(v.blocks().nth(3), None);
+
+        v.set(3, false);
+        assert_eq!(v.get(3), Some(false));
+        assert_eq!(v.get(134), None);
+        assert!(!v[3] && v[4]);
+        assert_eq!(bits(&v).iter().rev().position(|&b| !b), Some(130));
+
+        v.truncate(65);
+        assert_eq!(v.blocks().collect::<Vec<_>>(), [!0 ^ (1 << 3), 1]);
+        v.grow(40, false);
+        v.grow(3, true);
+        assert_eq!(v.len(), 108);
+        assert_eq!(bits(&v).iter().filter(|&&b| b).count(), 64 + 3);
+        v.push(true);
+        assert!(v[108]);
+
+        let mut w = BitVec::with_capacity(200);
+        w.extend(bits(&v));
+        assert_eq!(w, v);
+        assert_eq!(bits(&v).into_iter().collect::<BitVec>(), v);
+
+        v.set_all();
+        assert_eq!(v, BitVec::from_elem(109, true));
+    }
+
+    #[test]
+    fn append() {
+        let pattern = |n: usize| (0..n).map(|i| i % 3 == 0 || i % 7 == 1).collect::<BitVec>();
+        for &a in [0, 1, 10, 63, 64, 65, 200].iter() {
+            for &b in [0, 1, 10, 63, 64, 65, 200].iter() {
+                let (x, y) = (pattern(a), pattern(b));
+                let mut appended = x.clone();
+                appended.append(&y);
+
+                let mut expected = x.clone();
+                expected.extend(bits(&y));
+                assert_eq!(appended, expected, "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn bytes() {
+        let bytes = [0b1000_0000, 0b0000_0001, 0xFF, 0x12, 0x34];
+        let v = BitVec::from_bytes(&bytes);
+        assert_eq!(v.len(), 40);
+        assert!(v[0] && !v[1] && v[15] && v[16]);
+        assert_eq!(v.to_bytes(), bytes);
+
+        let mut v = BitVec::from_elem(10, false);
+        v.set(0, true);
+        v.set(9, true);
+        assert_eq!(v.to_bytes(), [0b1000_0000, 0b0100_0000]);
+    }
+
+    #[test]
+    fn blocks() {
+        let v = BitVec::from_elem(70, true);
+        assert_eq!(v.as_blocks(), [!0, 0b11_1111]);
+        assert_eq!(BitVec::from_blocks(v.as_blocks().to_vec(), 70), v);
+        assert_eq!(BitVec::from_blocks(vec![1 << 63], 64).len(), 64);
+        assert_eq!(BitVec::from_blocks(vec![], 0), BitVec::new());
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_blocks_past_end() {
+        BitVec::from_blocks(vec![1 << 10], 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_out_of_bounds() {
+        BitVec::from_elem(10, true).set(10, false);
+    }
+}
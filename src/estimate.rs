@@ -1,3 +1,4 @@
+use float;
 use tables;
 
 /// Returns estimated bounds for π(*n*), the number of primes less
@@ -16,13 +17,24 @@ use tables;
 /// 1998.
 ///
 /// [pdf]: http://www.unilim.fr/laco/theses/1998/T1998_01.html
+///
+/// # Examples
+///
+/// ```rust
+/// let (lo, hi) = slow_primes::estimate_prime_pi(1_000_000);
+/// // π(10^6) = 78498
+/// assert!(lo <= 78498 && 78498 <= hi);
+///
+/// // small values are looked up exactly.
+/// assert_eq!(slow_primes::estimate_prime_pi(100), (25, 25));
+/// ```
 pub fn estimate_prime_pi(n: u64) -> (u64, u64) {
     if n < tables::SMALL_PRIME_PI.len() as u64 {
         let x = tables::SMALL_PRIME_PI[n as usize] as u64;
         (x, x)
     } else {
         let n_ = n as f64;
-        let lg = n_.ln();
+        let lg = float::ln(n_);
         let inv_lg = 1.0 / lg;
         let n_lg = n_ * inv_lg;
 
@@ -75,6 +87,20 @@ pub fn estimate_prime_pi(n: u64) -> (u64, u64) {
 /// de nombres premiers."][pdf] PhD diss., Université de Limoges, 1998.
 ///
 /// [pdf]: http://www.unilim.fr/laco/theses/1998/T1998_01.html
+///
+/// # Examples
+///
+/// The upper bound is a safe limit for a sieve that needs to contain
+/// the `n`th prime.
+///
+/// ```rust
+/// let (lo, hi) = slow_primes::estimate_nth_prime(10_000);
+/// // p_10000 = 104729
+/// assert!(lo <= 104729 && 104729 <= hi);
+///
+/// let sieve = slow_primes::Primes::sieve(hi as usize);
+/// assert_eq!(sieve.nth_prime(10_000 - 1), Some(104729));
+/// ```
 pub fn estimate_nth_prime(n: u64) -> (u64, u64) {
     if n == 0 {
         (0, 0)
@@ -84,8 +110,8 @@ pub fn estimate_nth_prime(n: u64) -> (u64, u64) {
         (x, x)
     } else {
         let n_ = n as f64;
-        let lg = n_.ln();
-        let lglg = lg.ln();
+        let lg = float::ln(n_);
+        let lglg = float::ln(lg);
 
         let lo = lg + lglg - 1.0 + if n > 3 {//13196 {
             // [2] Theorem 1.6
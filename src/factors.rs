@@ -0,0 +1,389 @@
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::slice;
+use std::vec::{self, Vec};
+
+/// (prime, exponent) pairs storing the prime factorisation of a
+/// number.
+///
+/// This dereferences to a slice of the pairs, and prints as a
+/// product of prime powers: `2^3 * 5`, or `2³ × 5` with the
+/// alternate flag (`{:#}`). The factorisation of 1 is empty, and
+/// prints as `1`.
+///
+/// # Examples
+///
+/// ```rust
+/// let sieve = slow_primes::Primes::sieve(100);
+/// let factors = sieve.factor(40).unwrap();
+///
+/// assert_eq!(factors, [(2, 3), (5, 1)]);
+/// assert_eq!(factors.to_string(), "2^3 * 5");
+/// assert_eq!(format!("{:#}", factors), "2³ × 5");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Factors(Vec<(usize, usize)>);
+
+impl Factors {
+    /// Create an empty factorisation, that is, that of 1.
+    pub fn new() -> Factors {
+        Factors(Vec::new())
+    }
+
+    /// Multiply out the factorisation, computing ∏ *p*<sup>*k*</sup>,
+    /// or return `None` if this overflows a `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.factor(360).unwrap().product(), Some(360));
+    /// assert_eq!(slow_primes::Factors::new().product(), Some(1));
+    /// ```
+    pub fn product(&self) -> Option<usize> {
+        let mut ret = 1usize;
+        for &(p, k) in self.0.iter() {
+            for _ in 0..k {
+                ret = ret.checked_mul(p)?;
+            }
+        }
+        Some(ret)
+    }
+
+    /// Compute the radical, the product of the distinct primes
+    /// ∏ *p*, or return `None` if this overflows a `usize`.
+    ///
+    /// For the `partial` factorisation in a
+    /// `FactorError::LargeCofactor`, this is the radical of the part of
+    /// the number that was factored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::{Primes, FactorError};
+    ///
+    /// let sieve = Primes::sieve(30);
+    /// assert_eq!(sieve.factor(72).unwrap().radical(), Some(6));
+    ///
+    /// match sieve.radical(4 * 9 * 31 * 31) {
+    ///     Err(FactorError::LargeCofactor { leftover, partial }) => {
+    ///         assert_eq!(leftover, 31 * 31);
+    ///         assert_eq!(partial.radical(), Some(6));
+    ///     }
+    ///     _ => unreachable!()
+    /// }
+    /// ```
+    pub fn radical(&self) -> Option<usize> {
+        self.0.iter().try_fold(1usize, |acc, &(p, _)| acc.checked_mul(p))
+    }
+
+    /// Compute the factorisation of the greatest common divisor of
+    /// the numbers factorised by `self` and `other`, taking the
+    /// smaller exponent of each prime.
+    ///
+    /// Both factorisations need to be sorted by prime, as those from
+    /// `Primes::factor` are.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    /// let a = sieve.factor(12).unwrap();
+    /// let b = sieve.factor(18).unwrap();
+    ///
+    /// assert_eq!(a.gcd(&b), [(2, 1), (3, 1)]);
+    /// assert_eq!(a.gcd(&b).product(), Some(6));
+    /// ```
+    pub fn gcd(&self, other: &Factors) -> Factors {
+        merge(&self.0, &other.0, cmp::min)
+    }
+
+    /// Compute the factorisation of the least common multiple of the
+    /// numbers factorised by `self` and `other`, taking the larger
+    /// exponent of each prime.
+    ///
+    /// Both factorisations need to be sorted by prime, as those from
+    /// `Primes::factor` are.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    /// let a = sieve.factor(4).unwrap();
+    /// let b = sieve.factor(6).unwrap();
+    ///
+    /// assert_eq!(a.lcm(&b), [(2, 2), (3, 1)]);
+    /// assert_eq!(a.lcm(&b).product(), Some(12));
+    /// ```
+    pub fn lcm(&self, other: &Factors) -> Factors {
+        merge(&self.0, &other.0, cmp::max)
+    }
+
+    // Direct access to the pairs, for filling in a factorisation in
+    // place.
+    pub(crate) fn pairs_mut(&mut self) -> &mut Vec<(usize, usize)> {
+        &mut self.0
+    }
+
+    /// Extract the underlying (prime, exponent) pairs.
+    pub fn into_vec(self) -> Vec<(usize, usize)> {
+        self.0
+    }
+}
+
+// Combine two sorted factorisations, with the exponent of each prime
+// given by `f` (a missing prime has exponent zero), dropping primes
+// with exponent zero.
+fn merge<F>(a: &[(usize, usize)], b: &[(usize, usize)], f: F) -> Factors
+    where F: Fn(usize, usize) -> usize
+{
+    let mut ret = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let (p, k, l) = match (a.get(i), b.get(j)) {
+            (Some(&(p, k)), Some(&(q, _))) if p < q => { i += 1; (p, k, 0) }
+            (Some(&(p, _)), Some(&(q, l))) if q < p => { j += 1; (q, 0, l) }
+            (Some(&(p, k)), Some(&(_, l))) => { i += 1; j += 1; (p, k, l) }
+            (Some(&(p, k)), None) => { i += 1; (p, k, 0) }
+            (None, Some(&(q, l))) => { j += 1; (q, 0, l) }
+            (None, None) => unreachable!(),
+        };
+        let exponent = f(k, l);
+        if exponent > 0 {
+            ret.push((p, exponent))
+        }
+    }
+    Factors(ret)
+}
+
+impl Deref for Factors {
+    type Target = [(usize, usize)];
+    fn deref(&self) -> &[(usize, usize)] {
+        &self.0
+    }
+}
+
+impl From<Vec<(usize, usize)>> for Factors {
+    fn from(v: Vec<(usize, usize)>) -> Factors {
+        Factors(v)
+    }
+}
+
+impl From<Factors> for Vec<(usize, usize)> {
+    fn from(f: Factors) -> Vec<(usize, usize)> {
+        f.0
+    }
+}
+
+impl FromIterator<(usize, usize)> for Factors {
+    fn from_iter<I: IntoIterator<Item = (usize, usize)>>(iter: I) -> Factors {
+        Factors(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Factors {
+    type Item = (usize, usize);
+    type IntoIter = vec::IntoIter<(usize, usize)>;
+    fn into_iter(self) -> vec::IntoIter<(usize, usize)> {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Factors {
+    type Item = &'a (usize, usize);
+    type IntoIter = slice::Iter<'a, (usize, usize)>;
+    fn into_iter(self) -> slice::Iter<'a, (usize, usize)> {
+        self.0.iter()
+    }
+}
+
+impl PartialEq<[(usize, usize)]> for Factors {
+    fn eq(&self, other: &[(usize, usize)]) -> bool {
+        self.0[..] == *other
+    }
+}
+
+impl<'a> PartialEq<&'a [(usize, usize)]> for Factors {
+    fn eq(&self, other: &&'a [(usize, usize)]) -> bool {
+        self.0[..] == **other
+    }
+}
+
+impl<const N: usize> PartialEq<[(usize, usize); N]> for Factors {
+    fn eq(&self, other: &[(usize, usize); N]) -> bool {
+        self.0[..] == other[..]
+    }
+}
+
+impl PartialEq<Vec<(usize, usize)>> for Factors {
+    fn eq(&self, other: &Vec<(usize, usize)>) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Factors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("1")
+        }
+
+        let fancy = f.alternate();
+        for (i, &(p, k)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(if fancy { " × " } else { " * " })?;
+            }
+            write!(f, "{}", p)?;
+            if k != 1 {
+                if fancy {
+                    write_superscript(f, k)?;
+                } else {
+                    write!(f, "^{}", k)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_superscript(f: &mut fmt::Formatter, k: usize) -> fmt::Result {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    if k >= 10 {
+        write_superscript(f, k / 10)?;
+    }
+    write!(f, "{}", DIGITS[k % 10])
+}
+
+/// The reasons `Primes::factor` can fail to factorise a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FactorError {
+    /// Zero has no prime factorisation.
+    Zero,
+    /// The number had prime factors too large for the sieve, that
+    /// is, there was
+    ///
+    /// - a prime factor larger than `U^2`, or
+    /// - more than one prime factor between `U` and `U^2`
+    ///
+    /// where `U` is the upper bound of the primes stored in the
+    /// sieve.
+    ///
+    /// Notably, any number between `U` and `U^2` can always be fully
+    /// factored, since these numbers are guaranteed to only have zero
+    /// or one prime factors larger than `U`.
+    LargeCofactor {
+        /// The part of the number left after removing every prime
+        /// stored in the sieve. All of its prime factors are larger
+        /// than `U`, and it may be prime or composite.
+        leftover: usize,
+        /// The factorisation of the rest of the number, that is, the
+        /// original number divided by `leftover`.
+        partial: Factors,
+    },
+}
+
+impl fmt::Display for FactorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FactorError::Zero => f.write_str("cannot factorise zero"),
+            FactorError::LargeCofactor { leftover, ref partial } => {
+                write!(f, "cofactor {} is too large to factorise (after {})",
+                       leftover, partial)
+            }
+        }
+    }
+}
+
+impl Error for FactorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Factors, FactorError};
+
+    #[test]
+    fn display() {
+        type Test<'a> = (&'a [(usize, usize)], &'a str, &'a str);
+        let tests: &[Test] = &[
+            (&[], "1", "1"),
+            (&[(2, 1)], "2", "2"),
+            (&[(2, 3), (5, 1)], "2^3 * 5", "2³ × 5"),
+            (&[(3, 1), (7, 12), (101, 1)], "3 * 7^12 * 101", "3 × 7¹² × 101"),
+            (&[(2, 10)], "2^10", "2¹⁰"),
+            ];
+        for &(pairs, plain, fancy) in tests.iter() {
+            let f = Factors::from(pairs.to_vec());
+            assert_eq!(f.to_string(), plain);
+            assert_eq!(format!("{:#}", f), fancy);
+        }
+    }
+
+    #[test]
+    fn gcd_lcm() {
+        let a = Factors::from(vec![(2, 3), (5, 1), (7, 2)]);
+        let b = Factors::from(vec![(3, 1), (5, 2), (7, 2), (11, 1)]);
+        assert_eq!(a.gcd(&b), [(5, 1), (7, 2)]);
+        assert_eq!(a.lcm(&b), [(2, 3), (3, 1), (5, 2), (7, 2), (11, 1)]);
+        assert_eq!(b.gcd(&a), a.gcd(&b));
+        assert_eq!(b.lcm(&a), a.lcm(&b));
+
+        let one = Factors::new();
+        assert_eq!(a.gcd(&one), one);
+        assert_eq!(a.lcm(&one), a);
+        assert_eq!(one.lcm(&one), one);
+        // coprime
+        let c = Factors::from(vec![(13, 4)]);
+        assert_eq!(a.gcd(&c), one);
+        assert_eq!(a.lcm(&c), [(2, 3), (5, 1), (7, 2), (13, 4)]);
+    }
+
+    #[test]
+    fn conversions() {
+        let v = vec![(2, 3), (5, 1)];
+        let f = Factors::from(v.clone());
+        assert_eq!(f, v);
+        assert_eq!(f, [(2, 3), (5, 1)]);
+        assert_eq!(f, &v[..]);
+        assert_eq!(f.len(), 2);
+        assert_eq!(f.iter().map(|&(p, _)| p).collect::<Vec<_>>(), [2, 5]);
+        assert_eq!((&f).into_iter().count(), 2);
+        assert_eq!(v.iter().cloned().collect::<Factors>(), f);
+        assert_eq!(f.clone().into_vec(), v);
+        assert_eq!(Vec::from(f), v);
+        assert_eq!(Factors::new(), Factors::default());
+        assert!(Factors::new().is_empty());
+    }
+
+    #[test]
+    fn product() {
+        assert_eq!(Factors::new().product(), Some(1));
+        assert_eq!(Factors::from(vec![(2, 3), (5, 1)]).product(), Some(40));
+
+        let bits = ::std::mem::size_of::<usize>() * 8;
+        assert_eq!(Factors::from(vec![(2, bits - 1)]).product(), Some(1 << (bits - 1)));
+        assert_eq!(Factors::from(vec![(2, bits)]).product(), None);
+        assert_eq!(Factors::from(vec![(2, bits - 1), (3, 1)]).product(), None);
+
+        let primes = ::Primes::sieve(1000);
+        for n in 1..100_000 {
+            assert_eq!(primes.factor(n).unwrap().product(), Some(n));
+        }
+    }
+
+    #[test]
+    fn radical() {
+        assert_eq!(Factors::new().radical(), Some(1));
+        assert_eq!(Factors::from(vec![(2, 3), (3, 2)]).radical(), Some(6));
+        assert_eq!(Factors::from(vec![(2, 1000), (5, 1)]).radical(), Some(10));
+        assert_eq!(Factors::from(vec![(1 << 32, 1), ((1 << 32) + 1, 1)]).radical(), None);
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq!(FactorError::Zero.to_string(), "cannot factorise zero");
+        let e = FactorError::LargeCofactor { leftover: 961, partial: vec![(2, 1)].into() };
+        assert_eq!(e.to_string(), "cofactor 961 is too large to factorise (after 2)");
+    }
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
-use std::collections::{BitVec};
+use bit_vec::BitVec;
 use std::{cmp};
+use std::vec::Vec;
 
 use Primes;
+use float;
 
 /// A segmented sieve that yields only a small run of primes at a
 /// time.
@@ -15,6 +17,7 @@ pub struct StreamingSieve {
     sieve: BitVec,
     primes: Vec<(usize, usize)>,
 
+    seg_size: usize,
     low: usize,
     current: usize,
     limit: usize,
@@ -28,15 +31,28 @@ impl StreamingSieve {
     /// Create a new instance of the streaming sieve that will
     /// correctly progressively filter primes up to `limit`.
     pub fn new(limit: usize) -> StreamingSieve {
-        let small = Primes::sieve((limit as f64).sqrt() as usize + 1);
-        let current = 2;
+        StreamingSieve::with_segment_size(limit, SEG_SIZE)
+    }
+
+    /// Create a new instance of the streaming sieve that will
+    /// correctly progressively filter primes up to `limit`, with each
+    /// chunk covering `seg_size` integers (rounded up to an even
+    /// number).
+    pub fn with_segment_size(limit: usize, seg_size: usize) -> StreamingSieve {
+        assert!(seg_size > 0, "StreamingSieve: segment size must be non-zero");
+        let seg_size = seg_size + seg_size % 2;
+
+        let small = Primes::sieve(float::sqrt(limit as f64) as usize + 1);
+        // 2 is not stored, so the sieving starts with 3.
+        let current = 3;
         let low = 0;
 
         StreamingSieve {
             small: small,
-            sieve: BitVec::from_elem(SEG_SIZE, false),
+            sieve: BitVec::from_elem(seg_size / 2, false),
             primes: vec![],
 
+            seg_size: seg_size,
             low: low,
             current: current,
             limit: limit
@@ -48,7 +64,7 @@ impl StreamingSieve {
     ///
     /// The vector stores bits for each odd number starting at `low`.
     /// Bit `n` of `v` is set if and only if `low + 2 * n + 1` is
-    /// prime.
+    /// prime. Bits for numbers above the limit are meaningless.
     ///
     /// NB. the prime 2 is not included in any of these sieves and so
     /// needs special handling.
@@ -58,25 +74,26 @@ impl StreamingSieve {
         }
 
         let low = self.low;
-        self.low += SEG_SIZE;
-        let high = cmp::min(low + SEG_SIZE - 1, self.limit);
+        self.low += self.seg_size;
+        let high = cmp::min(low + self.seg_size - 1, self.limit);
         self.sieve.set_all();
 
         while self.current * self.current <= high {
             if self.small.is_prime(self.current) {
                 self.primes.push((self.current, self.current * self.current - low));
             }
-            self.current += 1
+            self.current += 2
         }
+        let half = self.seg_size / 2;
         for &mut (k, ref mut next) in self.primes.iter_mut() {
             let mut j = *next / 2;
-            while j < SEG_SIZE / 2 {
+            while j < half {
                 self.sieve.set(j, false);
                 j += k;
             }
 
 
-            *next = (2 * j + 1) - SEG_SIZE;
+            *next = (2 * j + 1) - self.seg_size;
         }
         if low == 0 {
             // 1 is not prime.
@@ -87,13 +104,112 @@ impl StreamingSieve {
     }
 }
 
+/// An iterator over all the primes up to some limit, sieving them a
+/// segment at a time.
+///
+/// Unlike `Primes`, this only needs `O(sqrt(limit) + segment_size)`
+/// memory, and so can enumerate primes far beyond what could be
+/// stored in a full sieve. The primes are yielded in ascending order,
+/// starting with 2, exactly as `Primes::sieve(limit).primes()` would
+/// (but without any primes above `limit`).
+///
+/// # Example
+///
+/// ```rust
+/// let primes = slow_primes::SegmentedSieve::new(30).collect::<Vec<_>>();
+/// assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// ```
+pub struct SegmentedSieve {
+    stream: StreamingSieve,
+    two: bool,
+    // the current segment, if one has been sieved yet.
+    segment: Option<usize>,
+    next_idx: usize,
+}
+
+impl SegmentedSieve {
+    /// Create an iterator over the primes less than or equal to `limit`,
+    /// using a segment size that fits into a typical L1 cache.
+    pub fn new(limit: usize) -> SegmentedSieve {
+        SegmentedSieve::with_segment_size(limit, SEG_SIZE)
+    }
+
+    /// Create an iterator over the primes less than or equal to
+    /// `limit`, with each segment covering `segment_size` integers.
+    ///
+    /// # Panics
+    ///
+    /// If `segment_size` is zero.
+    pub fn with_segment_size(limit: usize, segment_size: usize) -> SegmentedSieve {
+        SegmentedSieve {
+            stream: StreamingSieve::with_segment_size(limit, segment_size),
+            two: limit >= 2,
+            segment: None,
+            next_idx: 0,
+        }
+    }
+
+    /// The largest number this iterator will consider.
+    pub fn limit(&self) -> usize {
+        self.stream.limit
+    }
+
+    /// Check if `n` is prime, returning `None` if `n` is not inside
+    /// the segment that is currently sieved (i.e. the one containing
+    /// the most recently yielded odd prime).
+    pub fn is_prime(&self, n: usize) -> Option<bool> {
+        let low = self.segment?;
+        if n < low || n - low >= self.stream.seg_size || n > self.stream.limit {
+            None
+        } else if n % 2 == 0 {
+            Some(n == 2)
+        } else {
+            Some(self.stream.sieve[(n - low) / 2])
+        }
+    }
+}
+
+impl Iterator for SegmentedSieve {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.two {
+            self.two = false;
+            return Some(2)
+        }
+        loop {
+            if let Some(low) = self.segment {
+                let sieve = &self.stream.sieve;
+                while self.next_idx < sieve.len() {
+                    let i = self.next_idx;
+                    self.next_idx += 1;
+                    if sieve[i] {
+                        let p = low + 2 * i + 1;
+                        return if p <= self.stream.limit {
+                            Some(p)
+                        } else {
+                            None
+                        }
+                    }
+                }
+            }
+
+            match self.stream.next() {
+                Some((low, _)) => {
+                    self.segment = Some(low);
+                    self.next_idx = 0;
+                }
+                None => return None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use test::Bencher;
-    use super::StreamingSieve;
+    use super::{StreamingSieve, SegmentedSieve};
 
     #[test]
-    #[ignore(reason = "5 isn't a prime? should debug it, I guess.")]
     fn test() {
         let mut sieve = StreamingSieve::new(2000);
         let primes = ::Primes::sieve(2000);
@@ -105,7 +221,7 @@ mod tests {
             };
             println!("tick {}", next.len());
 
-            for i in (low + 1..low + next.len()).step_by(2) {
+            for i in (low + 1..low + 2 * next.len()).step_by(2) {
                 if i > 2000 { break }
                 assert!(primes.is_prime(i) == next[(i - low) / 2],
                         "failed for {} (is prime = {})", i, primes.is_prime(i));
@@ -113,6 +229,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn segmented() {
+        for &limit in [0, 1, 2, 3, 9, 10, 100, 1000, 10_001].iter() {
+            let primes = ::Primes::sieve(limit);
+            let expected = primes.primes().take_while(|&p| p <= limit).collect::<Vec<_>>();
+
+            assert_eq!(SegmentedSieve::new(limit).collect::<Vec<_>>(), expected);
+            // segment sizes that don't divide the limit, including odd ones.
+            for &seg in [1, 2, 10, 33, 128].iter() {
+                assert_eq!(SegmentedSieve::with_segment_size(limit, seg).collect::<Vec<_>>(),
+                           expected,
+                           "limit = {}, segment size = {}", limit, seg);
+            }
+        }
+    }
+
+    #[test]
+    fn segmented_is_prime() {
+        let primes = ::Primes::sieve(1000);
+        let mut sieve = SegmentedSieve::with_segment_size(1000, 100);
+        assert_eq!(sieve.is_prime(3), None);
+
+        while let Some(p) = sieve.next() {
+            if p == 2 { continue }
+            let low = p / 100 * 100;
+            for n in low..low + 100 {
+                assert_eq!(sieve.is_prime(n), Some(primes.is_prime(n)));
+            }
+            assert_eq!(sieve.is_prime(low + 100), None);
+            if low > 0 {
+                assert_eq!(sieve.is_prime(low - 1), None);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
+    use super::{StreamingSieve, SegmentedSieve};
+
     fn run(b: &mut Bencher, n: usize) {
         b.iter(|| {
             let mut sieve = StreamingSieve::new(n);
@@ -120,6 +277,11 @@ mod tests {
         })
     }
 
+    #[bench]
+    fn segmented_iterate_large(b: &mut Bencher) {
+        b.iter(|| SegmentedSieve::new(100_000).count())
+    }
+
     #[bench]
     fn sieve_small(b: &mut Bencher) {
         run(b, 100)
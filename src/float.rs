@@ -0,0 +1,23 @@
+//! The floating point functions used for estimates and square roots,
+//! which come from `libm` when `std` isn't available.
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn sqrt(x: f64) -> f64 { x.sqrt() }
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn sqrt(x: f64) -> f64 { ::libm::sqrt(x) }
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn ln(x: f64) -> f64 { x.ln() }
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn ln(x: f64) -> f64 { ::libm::log(x) }
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn powf(x: f64, y: f64) -> f64 { x.powf(y) }
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn powf(x: f64, y: f64) -> f64 { ::libm::pow(x, y) }
@@ -0,0 +1,247 @@
+use std::mem;
+
+use {Factors, FactorError, Primes};
+
+/// Compute the greatest common divisor of `a` and `b`, with the
+/// binary GCD algorithm.
+///
+/// `gcd(0, n) == gcd(n, 0) == n` for any `n`, since every number
+/// divides zero; in particular, `gcd(0, 0) == 0`.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::gcd;
+///
+/// assert_eq!(gcd(12, 18), 6);
+/// assert_eq!(gcd(17, 5), 1);
+/// assert_eq!(gcd(0, 7), 7);
+/// assert_eq!(gcd(0, 0), 0);
+/// ```
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    if a == 0 { return b }
+    if b == 0 { return a }
+
+    // the common factors of 2, then the odd parts.
+    let shift = (a | b).trailing_zeros();
+    a >>= a.trailing_zeros();
+    loop {
+        b >>= b.trailing_zeros();
+        // both are odd, so the difference is even (and is
+        // shifted away above).
+        if a > b {
+            mem::swap(&mut a, &mut b)
+        }
+        b -= a;
+        if b == 0 {
+            return a << shift
+        }
+    }
+}
+
+/// Compute the least common multiple of `a` and `b`, or `None` if it
+/// overflows a `u64`.
+///
+/// The least common multiple with zero is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::lcm;
+///
+/// assert_eq!(lcm(4, 6), Some(12));
+/// assert_eq!(lcm(0, 6), Some(0));
+/// assert_eq!(lcm(1 << 40, 3 << 30), Some(3 << 40));
+/// assert_eq!(lcm(1 << 40, (1 << 40) - 1), None);
+/// ```
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 { return Some(0) }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Check if `a` and `b` have no common factor other than 1, that is,
+/// if `gcd(a, b) == 1`.
+///
+/// Every number divides 0, and so `0` is only coprime to `1`.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::is_coprime;
+///
+/// assert!(is_coprime(8, 15));
+/// assert!(!is_coprime(8, 14));
+/// assert!(is_coprime(0, 1));
+/// assert!(!is_coprime(0, 0));
+/// ```
+pub fn is_coprime(a: u64, b: u64) -> bool {
+    gcd(a, b) == 1
+}
+
+impl Primes {
+    /// Compute the greatest common divisor of two numbers from their
+    /// factorisations, as `a.gcd(b)`.
+    ///
+    /// This works directly on the prime exponents, without needing
+    /// the numbers themselves (which may not even fit in a `usize`),
+    /// or a sieve. See `Factors::lcm` for the least common multiple.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::Primes;
+    ///
+    /// let sieve = Primes::sieve(100);
+    /// let a = sieve.factor(360).unwrap();
+    /// let b = sieve.factor(84).unwrap();
+    ///
+    /// assert_eq!(Primes::gcd_from_factors(&a, &b), [(2, 2), (3, 1)]);
+    /// ```
+    pub fn gcd_from_factors(a: &Factors, b: &Factors) -> Factors {
+        a.gcd(b)
+    }
+
+    /// Factorise the greatest common divisor of `a` and `b`, by
+    /// factorising both and taking the smaller exponent of each
+    /// prime.
+    ///
+    /// This shows which prime factors are shared, not just their
+    /// product (the free function `gcd` is faster for that). Since
+    /// `gcd(0, n) == n`, a zero is ignored, and `gcd(0, 0) == 0` gives
+    /// `Err(FactorError::Zero)`. Otherwise, returns the same `Err` as
+    /// `factor` if `a` or `b` cannot be fully factored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.gcd_via_factors(12, 18), Ok(vec![(2, 1), (3, 1)].into()));
+    /// assert_eq!(sieve.gcd_via_factors(12, 18).unwrap().product(), Some(6));
+    /// assert_eq!(sieve.gcd_via_factors(0, 18), sieve.factor(18));
+    /// ```
+    pub fn gcd_via_factors(&self, a: usize, b: usize) -> Result<Factors, FactorError> {
+        match (a, b) {
+            (0, n) | (n, 0) => self.factor(n),
+            _ => Ok(self.factor(a)?.gcd(&self.factor(b)?))
+        }
+    }
+
+    /// Compute the least common multiple of `a` and `b`, by
+    /// factorising both and taking the larger exponent of each prime.
+    ///
+    /// Returns `Ok(None)` if the result overflows a `usize`, and the
+    /// same `Err` as `factor` if `a` or `b` cannot be fully factored.
+    /// The least common multiple with zero is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.lcm(4, 6), Ok(Some(12)));
+    /// assert_eq!(sieve.lcm(0, 6), Ok(Some(0)));
+    /// ```
+    pub fn lcm(&self, a: usize, b: usize) -> Result<Option<usize>, FactorError> {
+        if a == 0 || b == 0 { return Ok(Some(0)) }
+        Ok(self.factor(a)?.lcm(&self.factor(b)?).product())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Primes, FactorError};
+    use super::{gcd, lcm, is_coprime};
+
+    fn euclid(a: u64, b: u64) -> u64 {
+        if b == 0 { a } else { euclid(b, a % b) }
+    }
+
+    #[test]
+    fn small() {
+        for a in 0..300 {
+            for b in 0..300 {
+                let g = gcd(a, b);
+                assert_eq!(g, euclid(a, b), "gcd({}, {})", a, b);
+                assert_eq!(g, gcd(b, a));
+                assert_eq!(is_coprime(a, b), g == 1);
+                if g != 0 {
+                    assert!(a % g == 0 && b % g == 0);
+                }
+
+                let l = lcm(a, b).unwrap();
+                assert_eq!(g * l, a * b, "lcm({}, {})", a, b);
+                if l != 0 {
+                    assert!(l % a == 0 && l % b == 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large() {
+        let max = 0xFFFF_FFFF_FFFF_FFFF;
+        assert_eq!(gcd(max, max), max);
+        assert_eq!(gcd(max, 0), max);
+        assert_eq!(gcd(1 << 63, 1 << 62), 1 << 62);
+        assert_eq!(gcd(max, max - 1), 1);
+        // u64::MAX = 3 * 5 * 17 * 257 * 641 * 65537 * 6700417
+        assert_eq!(gcd(max, 641 * 6700417 * 2), 641 * 6700417);
+        assert_eq!(gcd(4_294_967_291 * 3, 4_294_967_291 * 7), 4_294_967_291);
+
+        assert_eq!(lcm(max, max), Some(max));
+        assert_eq!(lcm(max, 1), Some(max));
+        assert_eq!(lcm(max, 2), None);
+        assert_eq!(lcm(1 << 32, 1 << 31), Some(1 << 32));
+        assert_eq!(lcm(1 << 32, (1 << 32) - 1), Some(0xFFFF_FFFF_0000_0000));
+        assert_eq!(lcm(4_294_967_291, 4_294_967_279), Some(4_294_967_291 * 4_294_967_279));
+        assert_eq!(lcm(1 << 63, 3), None);
+        assert_eq!(lcm(0, 0), Some(0));
+        assert_eq!(lcm(0, max), Some(0));
+    }
+
+    #[test]
+    fn via_factors() {
+        let sieve = Primes::sieve(100);
+        assert_eq!(sieve.lcm(4, 6), Ok(Some(12)));
+        assert_eq!(sieve.gcd_via_factors(12, 18).unwrap().product(), Some(6));
+
+        for a in 0..300 {
+            for b in 0..300 {
+                let (a_, b_) = (a as u64, b as u64);
+                assert_eq!(sieve.lcm(a, b), Ok(lcm(a_, b_).map(|l| l as usize)));
+                match sieve.gcd_via_factors(a, b) {
+                    Ok(factors) => assert_eq!(factors.product(), Some(gcd(a_, b_) as usize)),
+                    Err(e) => {
+                        assert_eq!((a, b), (0, 0));
+                        assert_eq!(e, FactorError::Zero);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(sieve.lcm(1 << 63, 3), Ok(None));
+        assert_eq!(sieve.lcm(1 << 63, 2), Ok(Some(1 << 63)));
+        assert_eq!(sieve.lcm(3 << 62, 3 << 61), Ok(Some(3 << 62)));
+
+        let small = Primes::sieve(30);
+        let large = FactorError::LargeCofactor { leftover: 31 * 31, partial: vec![(2, 1)].into() };
+        assert_eq!(small.lcm(2 * 31 * 31, 3), Err(large.clone()));
+        assert_eq!(small.gcd_via_factors(3, 2 * 31 * 31), Err(large));
+    }
+
+    #[test]
+    fn from_factors() {
+        let sieve = Primes::sieve(1000);
+        for a in 1..200 {
+            let fa = sieve.factor(a).unwrap();
+            for b in 1..200 {
+                let fb = sieve.factor(b).unwrap();
+                let g = Primes::gcd_from_factors(&fa, &fb);
+                assert_eq!(g.product(), Some(gcd(a as u64, b as u64) as usize), "{} {}", a, b);
+                assert_eq!(g, sieve.factor(gcd(a as u64, b as u64) as usize).unwrap());
+                assert_eq!(fa.lcm(&fb).product(), lcm(a as u64, b as u64).map(|l| l as usize));
+            }
+        }
+    }
+}
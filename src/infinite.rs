@@ -0,0 +1,133 @@
+use std::cmp;
+use std::iter;
+
+use Primes;
+
+// the limit of the first sieve, before any growing.
+const INITIAL_LIMIT: usize = 1 << 10;
+
+/// An iterator over every prime, without an upper bound, created by
+/// `primes()`.
+///
+/// This stores the primes in a `Primes` sieve, which is doubled with
+/// `Primes::grow_to` each time the iterator runs past its upper
+/// bound, so the memory used is proportional to the largest prime
+/// yielded so far. It runs until the primes no longer fit in a
+/// `usize`.
+#[derive(Clone, Debug)]
+pub struct InfinitePrimes {
+    sieve: Primes,
+    // the next odd number to check, or `None` once they have all
+    // been checked.
+    next: Option<usize>,
+    two: bool,
+}
+
+/// Iterate over all the primes, in increasing order, without choosing
+/// an upper bound in advance.
+///
+/// This is for when it isn't known how many primes will be needed:
+/// if it is, `Primes::sieve` (along with `estimate_nth_prime`, to
+/// find the bound for a given number of primes) is a bit faster, and
+/// `SegmentedSieve` uses much less memory.
+///
+/// # Examples
+///
+/// ```rust
+/// let primes = slow_primes::primes();
+/// assert_eq!(primes.take(10).collect::<Vec<_>>(), [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+///
+/// // the first prime with 7 digits.
+/// let p = slow_primes::primes().find(|&p| p >= 1_000_000).unwrap();
+/// assert_eq!(p, 1_000_003);
+/// ```
+pub fn primes() -> InfinitePrimes {
+    InfinitePrimes {
+        sieve: Primes::sieve(INITIAL_LIMIT),
+        next: Some(3),
+        two: true,
+    }
+}
+
+impl InfinitePrimes {
+    /// The sieve storing the primes found so far, which covers at
+    /// least all the ones that have been yielded.
+    pub fn sieve(&self) -> &Primes {
+        &self.sieve
+    }
+}
+
+impl Iterator for InfinitePrimes {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.two {
+            self.two = false;
+            return Some(2)
+        }
+        loop {
+            let n = self.next?;
+            if n > self.sieve.upper_bound() {
+                let limit = cmp::max(self.sieve.upper_bound(), 1).saturating_mul(2);
+                self.sieve.grow_to(limit);
+                if n > self.sieve.upper_bound() {
+                    // it can't grow any more.
+                    self.next = None;
+                    return None
+                }
+            }
+            self.next = n.checked_add(2);
+            if self.sieve.is_prime(n) {
+                return Some(n)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.next {
+            Some(_) => (self.two as usize, None),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl iter::FusedIterator for InfinitePrimes {}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+    use super::primes;
+
+    #[test]
+    fn matches_sieve() {
+        let sieve = Primes::sieve(3_000_000);
+        let mut infinite = primes();
+        for p in sieve.primes() {
+            assert_eq!(infinite.next(), Some(p));
+        }
+        let next = sieve.primes().last().and_then(|p| ::next_prime(p as u64));
+        assert_eq!(infinite.next(), next.map(|p| p as usize));
+
+        assert!(primes().take_while(|&p| p < 1_000_000).eq(sieve.primes_in_range(0, 1_000_000)));
+        assert_eq!(primes().next(), Some(2));
+        assert_eq!(primes().nth(100_000), sieve.nth_prime(100_000));
+    }
+
+    #[test]
+    fn memory() {
+        // the sieve only grows as far as needed.
+        let mut infinite = primes();
+        assert!(infinite.sieve().upper_bound() <= 1024);
+        for p in infinite.by_ref().take(100) {
+            assert!(p <= 1024);
+        }
+        assert!(infinite.sieve().upper_bound() <= 1024);
+
+        let last = infinite.by_ref().take_while(|&p| p < 1_000_000).last().unwrap();
+        assert_eq!(last, 999_983);
+        let ub = infinite.sieve().upper_bound();
+        assert!((1_000_000..2_100_000).contains(&ub), "{}", ub);
+        // `take_while` used up 1_000_003.
+        assert_eq!(infinite.next(), Some(1_000_033));
+    }
+}
@@ -1,19 +1,32 @@
-fn mod_exp(mut x: u64, mut d: u64, n: u64) -> u64 {
-    let mut ret = 1;
-    while d != 0 {
-        if d % 2 == 1 {
-            ret *= x;
-            if ret >= n {
-                ret %= n;
-            }
-        }
-        d /= 2;
-        x *= x;
-        if x >= n {
-            x %= n;
+use Primes;
+use float;
+use modular::{mod_mul, mod_pow, jacobi};
+use tables;
+
+// primes up to this are used for trial division by `is_prime_u64`
+// when it is given a sieve.
+const TRIAL_DIVISION_LIMIT: usize = 1 << 10;
+
+/// Split `n - 1` into `(d, s)` with `n - 1 = d * 2^s` and `d` odd,
+/// for odd `n > 1`.
+fn decompose(n: u64) -> (u64, u32) {
+    let s = (n - 1).trailing_zeros();
+    ((n - 1) >> s, s)
+}
+
+/// Check if `n` is a strong probable prime to the base `a`, where
+/// `n - 1 = d * 2^s` with `d` odd.
+fn strong_probable_prime(n: u64, d: u64, s: u32, a: u64) -> bool {
+    let mut power = mod_pow(a, d, n);
+    if power == 1 { return true }
+
+    for _r in 0..s {
+        if power == n - 1 {
+            return true
         }
+        power = mod_mul(power, power, n);
     }
-    ret
+    false
 }
 
 /// Test if `n` is prime, using the deterministic version of the
@@ -39,42 +52,336 @@ pub fn is_prime_miller_rabin(n: u64) -> bool {
           (2_152_302_898_746, &[2, 3, 5, 7, 11]),
           (3_474_749_660_382, &[2, 3, 5, 7, 11, 13]),
           (341_550_071_728_320, &[2, 3, 5, 7, 11, 13, 17]),
-          (0xFFFF_FFFF_FFFF_FFFF, &[2, 3, 5, 7, 11, 13, 17, 19, 23])
+          (3_825_123_056_546_413_050, &[2, 3, 5, 7, 11, 13, 17, 19, 23]),
+          (0xFFFF_FFFF_FFFF_FFFF, &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37])
          ];
 
     if n % 2 == 0 { return n == 2 }
     if n == 1 { return false }
 
-    let mut d = n - 1;
-    let mut s = 0;
-    while d % 2 == 0 { d /= 2; s += 1 }
+    let (d, s) = decompose(n);
 
     let witnesses =
         WITNESSES.iter().find(|&&(hi, _)| hi >= n)
             .map(|&(_, wtnss)| wtnss).unwrap();
-    'next_witness: for &a in witnesses.iter() {
-        let mut power = mod_exp(a, d, n);
-        if power == 1 { continue 'next_witness }
+    witnesses.iter().all(|&a| strong_probable_prime(n, d, s, a))
+}
+
+/// Test if `n` is probably prime, using `rounds` rounds of the
+/// Miller-Rabin test with pseudo-random bases derived from `seed`.
+///
+/// A prime always passes; a composite number passes with probability
+/// at most 4<sup>-`rounds`</sup> (for any `seed`). The same `seed`
+/// always gives the same answer.
+///
+/// This is mainly useful as a cheap filter: `is_prime_miller_rabin`
+/// is exact (and not much slower) for `u64`s.
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(slow_primes::miller_rabin(1_000_000_007, 10, 1));
+/// // a strong pseudoprime to bases 2, 3, 5 and 7.
+/// assert!(!slow_primes::miller_rabin(3_215_031_751, 10, 1));
+/// ```
+pub fn miller_rabin(n: u64, rounds: u32, seed: u64) -> bool {
+    if n < 4 { return n >= 2 }
+    if n % 2 == 0 { return false }
+
+    let (d, s) = decompose(n);
+    let mut rng = XorShift::new(seed);
+    (0..rounds).all(|_| {
+        // a base in 2..n - 1
+        let a = 2 + rng.next() % (n - 3);
+        strong_probable_prime(n, d, s, a)
+    })
+}
+
+/// Test if `n` is probably prime, using `rounds` rounds of the
+/// Miller-Rabin test with the first `rounds` primes as bases.
+///
+/// A prime always passes. Unlike `miller_rabin`, the bases are fixed,
+/// so there are composites that pass a few rounds (such as 2047, a
+/// strong pseudoprime to base 2), but the first 12 primes are enough
+/// to be exact for every `u64`. At most 54 rounds (the primes below
+/// 256) are done.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::is_probably_prime;
+///
+/// assert!(is_probably_prime(1_000_000_007, 5));
+/// // a Carmichael number, which fools the Fermat test
+/// assert!(!is_probably_prime(561, 1));
+/// assert!(is_probably_prime(2047, 1));
+/// assert!(!is_probably_prime(2047, 2));
+/// ```
+pub fn is_probably_prime(n: u64, rounds: usize) -> bool {
+    if n < 4 { return n >= 2 }
+    if n % 2 == 0 { return false }
+
+    let (d, s) = decompose(n);
+    tables::SMALL_PRIMES.iter().take(rounds).all(|&a| {
+        // a multiple of n says nothing (and n is then the prime a).
+        let a = a as u64 % n;
+        a == 0 || strong_probable_prime(n, d, s, a)
+    })
+}
+
+/// Test if `n` is prime, using the Baillie-PSW test.
+///
+/// This is a base-2 strong probable prime test followed by a strong
+/// Lucas probable prime test, with parameters chosen by Selfridge's
+/// method. No composite number is known to pass both, and there are
+/// certainly none below 2<sup>64</sup>, so this is exact for every
+/// `u64`.
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(slow_primes::is_prime_bpsw(1_000_000_007));
+/// // a strong pseudoprime to base 2.
+/// assert!(!slow_primes::is_prime_bpsw(2047));
+/// ```
+pub fn is_prime_bpsw(n: u64) -> bool {
+    for &p in tables::SMALL_PRIMES.iter() {
+        let p = p as u64;
+        if p * p > n { return n >= 2 }
+        if n % p == 0 { return n == p }
+    }
+
+    let (d, s) = decompose(n);
+    if !strong_probable_prime(n, d, s, 2) {
+        return false
+    }
+
+    // there's no suitable D for perfect squares, so they need to be
+    // handled separately.
+    let root = isqrt(n);
+    if root * root == n {
+        return false
+    }
+
+    // Selfridge's method A: the first of 5, -7, 9, -11, ... with
+    // (D/n) = -1.
+    let mut d: i64 = 5;
+    loop {
+        match jacobi(d, n) {
+            -1 => break,
+            // D shares a factor with n.
+            0 => return d.unsigned_abs() == n,
+            _ => {}
+        }
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
 
-        for _r in 0..s {
-            if power == n - 1 {
-                continue 'next_witness
+    strong_lucas_probable_prime(n, d, (1 - d) / 4)
+}
+
+/// The largest `r` with `r * r <= n`.
+pub(crate) fn isqrt(n: u64) -> u64 {
+    let mut r = float::sqrt(n as f64) as u64;
+    // the floating point approximation can be off by one in either
+    // direction.
+    while r as u128 * r as u128 > n as u128 {
+        r -= 1
+    }
+    while (r + 1) as u128 * (r + 1) as u128 <= n as u128 {
+        r += 1
+    }
+    r
+}
+
+/// Check if the odd `n` (coprime to `q` and `d`) is a strong Lucas
+/// probable prime with respect to the sequences with parameters `P =
+/// 1`, `Q = q`, and discriminant `d = P^2 - 4Q`.
+fn strong_lucas_probable_prime(n: u64, d: i64, q: i64) -> bool {
+    let m = n as u128;
+    let to_mod = |x: i64| (x as i128).rem_euclid(n as i128) as u64;
+    let add = |x: u64, y: u64| ((x as u128 + y as u128) % m) as u64;
+    let sub = |x: u64, y: u64| ((x as u128 + m - y as u128) % m) as u64;
+    // x / 2 (mod n), since n is odd.
+    let half = |x: u64| if x % 2 == 0 { x / 2 } else { ((x as u128 + m) / 2) as u64 };
+
+    let d_ = to_mod(d);
+    let q_ = to_mod(q);
+
+    // n + 1 = k * 2^s, with k odd.
+    let s = (m + 1).trailing_zeros();
+    let k = (m + 1) >> s;
+
+    // compute U_k, V_k and Q^k working down from the top bit of k,
+    // starting at U_1 = 1, V_1 = P = 1.
+    let mut u = 1;
+    let mut v = 1;
+    let mut q_k = q_;
+    for bit in (0..128 - k.leading_zeros() - 1).rev() {
+        // U_2j = U_j V_j, V_2j = V_j^2 - 2 Q^j
+        u = mod_mul(u, v, n);
+        v = sub(mod_mul(v, v, n), add(q_k, q_k));
+        q_k = mod_mul(q_k, q_k, n);
+
+        if (k >> bit) & 1 == 1 {
+            // U_(j+1) = (P U_j + V_j) / 2, V_(j+1) = (D U_j + P V_j) / 2
+            let u_ = half(add(u, v));
+            v = half(add(mod_mul(d_, u, n), v));
+            u = u_;
+            q_k = mod_mul(q_k, q_, n);
+        }
+    }
+
+    if u == 0 || v == 0 {
+        return true
+    }
+    for _r in 1..s {
+        v = sub(mod_mul(v, v, n), add(q_k, q_k));
+        q_k = mod_mul(q_k, q_k, n);
+        if v == 0 {
+            return true
+        }
+    }
+    false
+}
+
+// A tiny xorshift* generator, for choosing Miller-Rabin bases without
+// depending on a random number crate.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> XorShift {
+        // the state must be non-zero.
+        XorShift(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+/// Test if `n` is prime, exactly, for any `u64`.
+///
+/// Tiny factors are found by trial division, using the primes stored
+/// in `primes` if it is given (and answering directly from the sieve
+/// if `n` is small enough to be stored in it), or a small built-in
+/// table of primes otherwise. Anything that survives this is checked
+/// with the deterministic Miller-Rabin test.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::{Primes, is_prime_u64};
+///
+/// assert!(is_prime_u64(1_000_000_007, None));
+/// // the largest prime that fits in a u64.
+/// assert!(is_prime_u64(18_446_744_073_709_551_557, None));
+///
+/// let sieve = Primes::sieve(1000);
+/// assert!(!is_prime_u64(1_000_000_011, Some(&sieve)));
+/// ```
+pub fn is_prime_u64(n: u64, primes: Option<&Primes>) -> bool {
+    // `Some(is_prime)` if trial division was enough to decide.
+    fn trial_division<I: Iterator<Item = u64>>(n: u64, iter: I) -> Option<bool> {
+        for p in iter {
+            if p > n / p {
+                return Some(true)
             }
-            power *= power;
-            if power >= n {
-                power %= n;
+            if n % p == 0 {
+                return Some(n == p)
             }
         }
-        return false
+        None
     }
 
-    true
+    if n < 2 { return false }
+
+    let known = match primes {
+        Some(primes) => {
+            if n <= primes.upper_bound() as u64 {
+                return primes.is_prime(n as usize)
+            }
+            trial_division(n, primes.primes()
+                           .take_while(|&p| p <= TRIAL_DIVISION_LIMIT)
+                           .map(|p| p as u64))
+        }
+        None => trial_division(n, tables::SMALL_PRIMES.iter().map(|&p| p as u64))
+    };
+
+    known.unwrap_or_else(|| is_prime_miller_rabin(n))
+}
+
+// whether `n` could be prime, given it's odd and at least 7.
+fn wheel_candidate(n: u64) -> bool {
+    n % 3 != 0 && n % 5 != 0
+}
+
+/// Return the smallest prime strictly larger than `n`, or `None` if
+/// there is no such `u64` (that is, `n` is at least
+/// 18446744073709551557, the largest prime that fits).
+///
+/// Only odd candidates that aren't multiples of 3 or 5 are tested,
+/// each with `is_prime_u64`, so this works for any `n` without
+/// needing a sieve. The first prime at least `n` is `next_prime(n -
+/// 1)`.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::next_prime;
+///
+/// assert_eq!(next_prime(0), Some(2));
+/// assert_eq!(next_prime(7), Some(11));
+/// assert_eq!(next_prime(1 << 32), Some(4_294_967_311));
+/// assert_eq!(next_prime(18_446_744_073_709_551_557), None);
+/// ```
+pub fn next_prime(n: u64) -> Option<u64> {
+    if n < 7 {
+        return [2, 3, 5, 7].iter().cloned().find(|&p| p > n)
+    }
+    // the first odd number after n.
+    let mut c = if n % 2 == 0 { n + 1 } else { n.checked_add(2)? };
+    while !(wheel_candidate(c) && is_prime_u64(c, None)) {
+        c = c.checked_add(2)?;
+    }
+    Some(c)
+}
+
+/// Return the largest prime strictly smaller than `n`, or `None` if
+/// there is no such prime (`n <= 2`).
+///
+/// Like `next_prime`, this tests odd candidates that aren't multiples
+/// of 3 or 5 with `is_prime_u64`, so works for any `n`.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::prev_prime;
+///
+/// assert_eq!(prev_prime(2), None);
+/// assert_eq!(prev_prime(3), Some(2));
+/// assert_eq!(prev_prime(1 << 32), Some(4_294_967_291));
+/// assert_eq!(prev_prime(0xFFFF_FFFF_FFFF_FFFF), Some(18_446_744_073_709_551_557));
+/// ```
+pub fn prev_prime(n: u64) -> Option<u64> {
+    if n <= 7 {
+        return [5, 3, 2].iter().cloned().find(|&p| p < n)
+    }
+    // the last odd number before n, which is at least 7.
+    let mut c = if n % 2 == 0 { n - 1 } else { n - 2 };
+    while !(wheel_candidate(c) && is_prime_u64(c, None)) {
+        c -= 2;
+    }
+    Some(c)
 }
 
 #[cfg(test)]
 mod tests {
     use Primes;
-    use super::is_prime_miller_rabin;
+    use super::{is_prime_miller_rabin, is_prime_u64, is_prime_bpsw, is_probably_prime};
+    use modular::mod_pow;
 
     #[test]
     fn miller_rabin() {
@@ -88,4 +395,176 @@ mod tests {
                     mr, s, x)
         }
     }
+
+    #[test]
+    fn miller_rabin_large() {
+        let tests = [
+            (4_294_967_291, true),
+            (4_294_967_291 * 4_294_967_279, false),
+            ((1 << 61) - 1, true),
+            // strong pseudoprime to all bases up to 23.
+            (3_825_123_056_546_413_051, false),
+            (18_446_744_073_709_551_557, true),
+            (0xFFFF_FFFF_FFFF_FFFF, false),
+            ];
+
+        for &(n, expected) in tests.iter() {
+            assert_eq!(is_prime_miller_rabin(n), expected);
+            assert_eq!(is_prime_u64(n, None), expected);
+        }
+    }
+
+    #[test]
+    fn u64() {
+        const LIMIT: usize = 100_000;
+        let sieve = Primes::sieve(LIMIT);
+        let small = Primes::sieve(100);
+        let medium = Primes::sieve(5000);
+        for x in 0..LIMIT {
+            let expected = sieve.is_prime(x);
+            let x = x as u64;
+            assert_eq!(is_prime_u64(x, None), expected, "{}", x);
+            assert_eq!(is_prime_u64(x, Some(&small)), expected, "{}", x);
+            assert_eq!(is_prime_u64(x, Some(&medium)), expected, "{}", x);
+        }
+        assert!(is_prime_u64(18_446_744_073_709_551_557, Some(&medium)));
+        assert!(!is_prime_u64(1009 * 1_000_000_007, Some(&medium)));
+    }
+
+    #[test]
+    fn next_prev_prime() {
+        const LIMIT: usize = 1_000_000;
+        let sieve = Primes::sieve(LIMIT + 100);
+        for n in 0..LIMIT {
+            assert_eq!(super::next_prime(n as u64), sieve.next_prime(n).map(|p| p as u64),
+                       "next_prime({})", n);
+            assert_eq!(super::prev_prime(n as u64), sieve.prev_prime(n).map(|p| p as u64),
+                       "prev_prime({})", n);
+        }
+
+        const MAX: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+        // the two largest primes that fit in a u64.
+        const P: u64 = 18_446_744_073_709_551_557;
+        const Q: u64 = 18_446_744_073_709_551_533;
+        for n in P..MAX {
+            assert_eq!(super::next_prime(n), None, "{}", n);
+        }
+        assert_eq!(super::next_prime(MAX), None);
+        assert_eq!(super::next_prime(P - 1), Some(P));
+        assert_eq!(super::next_prime(Q), Some(P));
+        assert_eq!(super::prev_prime(MAX), Some(P));
+        assert_eq!(super::prev_prime(P + 1), Some(P));
+        assert_eq!(super::prev_prime(P), Some(Q));
+
+        // around 2^32
+        assert_eq!(super::next_prime(4_294_967_291), Some(4_294_967_311));
+        assert_eq!(super::prev_prime(4_294_967_311), Some(4_294_967_291));
+    }
+
+    #[test]
+    fn probabilistic() {
+        const LIMIT: usize = 100_000;
+        let sieve = Primes::sieve(LIMIT);
+        for x in 0..LIMIT {
+            assert_eq!(super::miller_rabin(x as u64, 10, x as u64), sieve.is_prime(x), "{}", x);
+        }
+
+        let tests = [
+            (4_294_967_291, true),
+            ((1 << 61) - 1, true),
+            (18_446_744_073_709_551_557, true),
+            // Carmichael numbers
+            (561, false),
+            (1105, false),
+            (41_041, false),
+            // strong pseudoprimes to several small bases
+            (3_215_031_751, false),
+            (3_825_123_056_546_413_051, false),
+            (4_294_967_291 * 4_294_967_279, false),
+            ];
+        for &(n, expected) in tests.iter() {
+            for seed in 0..20 {
+                assert_eq!(super::miller_rabin(n, 10, seed), expected, "{} (seed {})", n, seed);
+            }
+        }
+        // no rounds can't rule anything out.
+        assert!(super::miller_rabin(3_215_031_751, 0, 0));
+    }
+
+    #[test]
+    fn fixed_bases() {
+        const LIMIT: usize = 100_000;
+        let sieve = Primes::sieve(LIMIT);
+        for x in 0..LIMIT {
+            assert_eq!(is_probably_prime(x as u64, 12), sieve.is_prime(x), "{}", x);
+            if sieve.is_prime(x) {
+                assert!(is_probably_prime(x as u64, 1) && is_probably_prime(x as u64, 100), "{}", x);
+            }
+        }
+
+        // Carmichael numbers pass the Fermat test to every coprime
+        // base, but not the strong test.
+        for &n in [561, 1105, 1729, 2465, 41_041, 825_265].iter() {
+            assert_eq!(mod_pow(2, n - 1, n), 1, "{}", n);
+            assert!(!is_probably_prime(n, 1), "{}", n);
+        }
+
+        // (n, the number of bases it's a strong pseudoprime to)
+        let pseudoprimes = [
+            (2047, 1),
+            (1_373_653, 2),
+            (25_326_001, 3),
+            (3_215_031_751, 4),
+            (3_825_123_056_546_413_051, 9),
+            ];
+        for &(n, bases) in pseudoprimes.iter() {
+            assert!(is_probably_prime(n, bases), "{}", n);
+            assert!(!is_probably_prime(n, 12), "{}", n);
+        }
+        assert!(!is_probably_prime(2047, 2));
+        assert!(is_probably_prime(18_446_744_073_709_551_557, 12));
+        assert!(!is_probably_prime(0xFFFF_FFFF_FFFF_FFFF, 12));
+        // no rounds can't rule anything out.
+        assert!(is_probably_prime(9, 0));
+        assert!(!is_probably_prime(10, 0));
+    }
+
+    #[test]
+    fn bpsw() {
+        const LIMIT: usize = 1_000_000;
+        let sieve = Primes::sieve(LIMIT);
+        for x in 0..LIMIT {
+            assert_eq!(is_prime_bpsw(x as u64), sieve.is_prime(x), "{}", x);
+        }
+
+        // strong pseudoprimes to base 2
+        let spsp = [2047, 3277, 4033, 4681, 8321, 15841, 29341, 42799, 49141,
+                    52633, 65281, 74665, 80581, 85489, 88357, 90751,
+                    3_215_031_751, 3_825_123_056_546_413_051];
+        for &n in spsp.iter() {
+            assert!(super::strong_probable_prime(n, (n - 1) >> (n - 1).trailing_zeros(),
+                                                 (n - 1).trailing_zeros(), 2));
+            assert!(!is_prime_bpsw(n), "{}", n);
+        }
+
+        let start = 0xFFFF_FFFF_FFFF_FFFF - 100_000;
+        for n in start..0xFFFF_FFFF_FFFF_FFFF {
+            assert_eq!(is_prime_bpsw(n), is_prime_miller_rabin(n), "{}", n);
+        }
+        assert!(!is_prime_bpsw(1_000_000_007 * 1_000_000_007));
+    }
+
+    #[test]
+    fn strong_lucas() {
+        // strong Lucas pseudoprimes, taking Selfridge's parameters.
+        let slpsp = [5459, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309, 58519];
+        for &n in slpsp.iter() {
+            let mut d: i64 = 5;
+            while super::jacobi(d, n) != -1 {
+                d = if d > 0 { -(d + 2) } else { -d + 2 };
+            }
+            assert!(super::strong_lucas_probable_prime(n, d, (1 - d) / 4), "{}", n);
+            assert!(!is_prime_bpsw(n), "{}", n);
+        }
+    }
 }
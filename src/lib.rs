@@ -54,40 +54,112 @@
 //! [dependencies.slow_primes]
 //! git = "https://github.com/huonw/slow_primes"
 //! ```
+//!
+//! # `no_std`
+//!
+//! The default `std` feature can be disabled to use this library
+//! with only `core` and `alloc`, for example in embedded or WASM
+//! contexts. Everything is available except for `Primes::write_to`,
+//! `Primes::read_from` and `Primes::sieve_parallel`, and the floating
+//! point functions come from `libm`. The `rayon` feature needs
+//! threads, and so turns `std` back on.
+//!
+//! ```toml
+//! [dependencies.slow_primes]
+//! git = "https://github.com/huonw/slow_primes"
+//! default-features = false
+//! ```
 
-#![feature(collections)]
-#![cfg_attr(test, feature(test, step_by))]
+#![cfg_attr(all(test, feature = "unstable"), feature(test))]
+// this predates these idioms, and it's clearer to be consistent.
+#![allow(clippy::redundant_field_names,
+         clippy::redundant_static_lifetimes,
+         clippy::manual_div_ceil,
+         clippy::manual_is_multiple_of)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(not(any(feature = "std", test)))]
+#[macro_use]
+extern crate alloc;
+#[cfg(not(feature = "std"))] extern crate libm;
 extern crate num as num_;
+#[cfg(feature = "rand")] extern crate rand;
+#[cfg(feature = "rayon")] extern crate rayon;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "smallvec")] extern crate smallvec;
+#[cfg(all(test, feature = "serde"))] extern crate bincode;
+#[cfg(all(test, feature = "serde"))] extern crate serde_json;
+
+#[cfg(all(test, feature = "unstable"))] extern crate test;
 
-#[cfg(test)] extern crate test;
+// Without `std`, provide the parts of it that this library uses from
+// `core` and `alloc`, so that the paths are the same either way.
+#[cfg(not(any(feature = "std", test)))]
+mod std {
+    pub use core::*;
+    pub use alloc::{fmt, slice, vec};
+}
 
+pub use arith::{Divisors, totient_table, radical_table};
+#[cfg(feature = "bigint")]
+pub use big::is_probable_prime_big;
 pub use estimate::{estimate_prime_pi, estimate_nth_prime};
-//pub use fast_sieve::Sieve;
-pub use is_prime::{is_prime_miller_rabin};
-pub use perfect_power::{as_perfect_power, as_prime_power};
-pub use sieve::{Primes, PrimeIterator};
+pub use factors::{Factors, FactorError};
+pub use fast_sieve::SegmentedSieve;
+pub use gcd::{gcd, lcm, is_coprime};
+pub use infinite::{primes, InfinitePrimes};
+pub use is_prime::{is_prime_miller_rabin, is_prime_u64, is_prime_bpsw, miller_rabin,
+                   is_probably_prime, next_prime, prev_prime};
+pub use mersenne::lucas_lehmer;
+pub use modular::{mod_mul, mod_pow, mod_inverse, jacobi, legendre, mod_sqrt,
+                  primitive_root};
+pub use perfect_power::{as_perfect_power, as_prime_power, perfect_power};
+pub use pollard_rho::factor_u64;
+#[cfg(feature = "rand")]
+pub use random::{gen_prime, gen_prime_in_range};
+pub use raw::ParseError;
+pub use sieve::{Primes, PrimeIterator, PrimeGaps, TwinPrimeIterator,
+                estimated_sieve_memory};
+#[cfg(feature = "smallvec")]
+pub use small::SmallFactors;
+pub use spf::SpfSieve;
+pub use wheel::{WheelPrimes, WheelPrimeIterator};
 
+mod arith;
+#[cfg(feature = "bigint")]
+mod big;
+mod bit_vec;
 mod estimate;
+mod factors;
 mod fast_sieve;
+mod float;
+mod gcd;
+mod infinite;
 mod is_prime;
+mod mersenne;
+mod modular;
+#[cfg(feature = "std")]
+mod parallel;
 mod perfect_power;
+mod pollard_rho;
+#[cfg(feature = "rand")]
+mod random;
+mod raw;
 mod sieve;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "smallvec")]
+mod small;
+mod spf;
+mod wheel;
 
 #[allow(dead_code)]
 mod tables;
 
-/// (prime, exponent) pairs storing the prime factorisation of a
-/// number.
-pub type Factors = Vec<(usize, usize)>;
-
-#[cfg(test)]
-mod tests {
-    extern crate test;
-
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
     use super::{Primes, is_prime_miller_rabin};
-    use self::test::Bencher;
-
 
     const N: usize = 1_000_000;
     const STEP: usize = 101;
@@ -0,0 +1,176 @@
+use Primes;
+use is_prime::is_prime_u64;
+
+/// The largest exponent `lucas_lehmer` can handle.
+const MAX_EXPONENT: u32 = 127;
+
+/// Compute `x * y` as a 256-bit number, returning the high and low
+/// halves.
+fn widening_mul(x: u128, y: u128) -> (u128, u128) {
+    const MASK: u128 = (1 << 64) - 1;
+    let (x_hi, x_lo) = (x >> 64, x & MASK);
+    let (y_hi, y_lo) = (y >> 64, y & MASK);
+
+    let lo = x_lo * y_lo;
+    let mid1 = x_hi * y_lo;
+    let mid2 = x_lo * y_hi;
+    let hi = x_hi * y_hi;
+
+    // add up the middle column, keeping track of the carries.
+    let mid = (lo >> 64) + (mid1 & MASK) + (mid2 & MASK);
+    let lo = (lo & MASK) | (mid << 64);
+    let hi = hi + (mid1 >> 64) + (mid2 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// Compute `x^2 mod 2^p - 1`, for `x < 2^p` and `p <= 127`.
+fn square_mod_mersenne(x: u128, p: u32) -> u128 {
+    let m = (1 << p) - 1;
+    let (hi, lo) = widening_mul(x, x);
+    // 2^p = 1 (mod m), so the bits above p can be folded back down
+    // onto the bottom. The square is less than 2^2p, so the high
+    // half is small enough not to overflow.
+    let mut r = (lo & m) + ((hi << (128 - p)) | (lo >> p));
+    while r >= m {
+        r -= m
+    }
+    r
+}
+
+/// Check if the Mersenne number 2<sup>`p`</sup> - 1 is prime, with
+/// the Lucas-Lehmer test.
+///
+/// 2<sup>`p`</sup> - 1 can only be prime if `p` is, so this returns
+/// `false` straight away for composite `p`. Otherwise, it computes
+/// *s*<sub>*p* - 2</sub> for *s*<sub>0</sub> = 4 and
+/// *s*<sub>*k* + 1</sub> = *s*<sub>*k*</sub><sup>2</sup> - 2, modulo
+/// 2<sup>`p`</sup> - 1, which is zero exactly when 2<sup>`p`</sup> -
+/// 1 is prime.
+///
+/// # Panics
+///
+/// If `p` is prime and larger than 127, since the arithmetic is
+/// done with `u128`s.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::lucas_lehmer;
+///
+/// assert!(lucas_lehmer(31));
+/// // 2^11 - 1 = 2047 = 23 * 89
+/// assert!(!lucas_lehmer(11));
+/// assert!(lucas_lehmer(127));
+/// ```
+pub fn lucas_lehmer(p: u32) -> bool {
+    if !is_prime_u64(p as u64, None) {
+        return false
+    }
+    // the recurrence doesn't work for 2^2 - 1 = 3.
+    if p == 2 {
+        return true
+    }
+    assert!(p <= MAX_EXPONENT,
+            "lucas_lehmer: exponent {} is larger than the maximum {}", p, MAX_EXPONENT);
+
+    let m = (1 << p) - 1;
+    let mut s = 4;
+    for _ in 0..p - 2 {
+        s = square_mod_mersenne(s, p);
+        s = if s >= 2 { s - 2 } else { s + m - 2 };
+    }
+    s == 0
+}
+
+impl Primes {
+    /// Iterator over the primes `p` stored in this map for which the
+    /// Mersenne number 2<sup>`p`</sup> - 1 is prime, in increasing
+    /// order, checked with `lucas_lehmer`.
+    ///
+    /// Only exponents up to 127 can be checked, so the iterator stops
+    /// there, even if the sieve is larger.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.mersenne_exponents().collect::<Vec<_>>(),
+    ///            [2, 3, 5, 7, 13, 17, 19, 31, 61, 89]);
+    /// ```
+    pub fn mersenne_exponents<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.primes_in_range(0, MAX_EXPONENT as usize + 1)
+            .filter(|&p| lucas_lehmer(p as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+    use is_prime::is_prime_u64;
+    use super::{lucas_lehmer, widening_mul, square_mod_mersenne};
+
+    const EXPONENTS: [usize; 12] = [2, 3, 5, 7, 13, 17, 19, 31, 61, 89, 107, 127];
+
+    #[test]
+    fn small() {
+        // compare against a direct primality test where it fits.
+        for p in 0..64 {
+            let expected = is_prime_u64(p as u64, None) && is_prime_u64((1 << p) - 1, None);
+            assert_eq!(lucas_lehmer(p), expected, "{}", p);
+        }
+        for &p in [11, 23, 29, 37, 41, 43, 47, 53, 59, 67, 71, 73, 79, 83, 97, 101, 103, 109, 113].iter() {
+            assert!(!lucas_lehmer(p), "{}", p);
+        }
+        // composite exponents
+        for &p in [0, 1, 4, 9, 15, 121, 1000, 1 << 20].iter() {
+            assert!(!lucas_lehmer(p), "{}", p);
+        }
+    }
+
+    #[test]
+    fn mersenne_exponents() {
+        let sieve = Primes::sieve(1000);
+        assert_eq!(sieve.mersenne_exponents().collect::<Vec<_>>(), EXPONENTS);
+        for &p in EXPONENTS.iter() {
+            assert!(lucas_lehmer(p as u32));
+        }
+
+        let small = Primes::sieve(30);
+        assert_eq!(small.mersenne_exponents().collect::<Vec<_>>(), &EXPONENTS[..7]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_large() {
+        lucas_lehmer(131);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let max = !0u128;
+        assert_eq!(widening_mul(max, max), (max - 1, 1));
+        assert_eq!(widening_mul(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(widening_mul(12345, 67890), (0, 12345 * 67890));
+
+        for &p in [3, 7, 31, 61, 64, 100, 127].iter() {
+            let m = (1u128 << p) - 1;
+            for &x in [0, 1, 2, m - 1, m / 3, 0xDEAD_BEEF].iter() {
+                let x = x % m;
+                let expected = if p <= 64 { x * x % m } else {
+                    // multiply by doubling, which can't overflow.
+                    let mut r = 0;
+                    let mut a = x;
+                    let mut b = x;
+                    while b > 0 {
+                        if b & 1 == 1 { r = (r + a) % m }
+                        a = (a + a) % m;
+                        b >>= 1;
+                    }
+                    r
+                };
+                assert_eq!(square_mod_mersenne(x, p), expected, "{}^2 mod 2^{} - 1", x, p);
+            }
+        }
+    }
+}
@@ -0,0 +1,646 @@
+use std::cmp;
+
+use Primes;
+use is_prime::is_prime_u64;
+use pollard_rho::factor_u64;
+
+/// Compute `a * b mod m`, without overflowing.
+///
+/// The product is computed in full with `u128`s, so this is correct
+/// for every `u64`, even when `a * b` doesn't fit.
+///
+/// # Panics
+///
+/// If `m` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::mod_mul;
+///
+/// assert_eq!(mod_mul(7, 8, 10), 6);
+/// let max = 0xFFFF_FFFF_FFFF_FFFF;
+/// // (-1) * (-2) = 2
+/// assert_eq!(mod_mul(max - 1, max - 2, max), 2);
+/// ```
+#[inline]
+pub fn mod_mul(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+/// Compute `base^exp mod modulus`, by repeated squaring.
+///
+/// Every intermediate product is computed with `mod_mul`, so this is
+/// correct for every `u64`. As usual, `base^0 = 1` (even when `base`
+/// is zero), and everything is 0 modulo 1.
+///
+/// # Panics
+///
+/// If `modulus` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::mod_pow;
+///
+/// assert_eq!(mod_pow(2, 10, 1000), 24);
+/// assert_eq!(mod_pow(0, 0, 7), 1);
+/// assert_eq!(mod_pow(5, 3, 1), 0);
+/// // Fermat's little theorem
+/// assert_eq!(mod_pow(3, 1_000_000_006, 1_000_000_007), 1);
+/// ```
+pub fn mod_pow(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    assert!(modulus != 0, "mod_pow: modulus must be non-zero");
+    let mut ret = 1 % modulus;
+    while exp != 0 {
+        if exp % 2 == 1 {
+            ret = mod_mul(ret, base, modulus);
+        }
+        exp /= 2;
+        base = mod_mul(base, base, modulus);
+    }
+    ret
+}
+
+/// Compute the inverse of `a` modulo `m`, that is, the `x` in
+/// `0..m` with `a * x = 1 (mod m)`, or `None` if there isn't one
+/// (when `a` and `m` aren't coprime).
+///
+/// This uses the extended Euclidean algorithm, with `i128`s for the
+/// coefficients so that nothing overflows. Everything is 0 modulo 1, so
+/// the inverse of anything modulo 1 is `Some(0)`.
+///
+/// # Panics
+///
+/// If `m` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::mod_inverse;
+///
+/// assert_eq!(mod_inverse(3, 10), Some(7));
+/// assert_eq!(mod_inverse(4, 10), None);
+/// assert_eq!(mod_inverse(0, 7), None);
+/// assert_eq!(mod_inverse(5, 1), Some(0));
+/// ```
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    assert!(m != 0, "mod_inverse: modulus must be non-zero");
+
+    // invariant: r_i = t_i * a (mod m)
+    let (mut r0, mut r1) = (m as i128, (a % m) as i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        let r2 = r0 - q * r1;
+        r0 = r1;
+        r1 = r2;
+        let t2 = t0 - q * t1;
+        t0 = t1;
+        t1 = t2;
+    }
+
+    // r0 is now gcd(a, m).
+    if r0 == 1 {
+        Some(t0.rem_euclid(m as i128) as u64)
+    } else {
+        None
+    }
+}
+
+/// Compute the Jacobi symbol (*a*/*n*), for odd positive `n`.
+///
+/// This is 0 if `a` and `n` share a factor, and otherwise ±1, the
+/// product of the Legendre symbols (*a*/*p*) over the prime factors
+/// *p* of `n` (with multiplicity). It's computed with quadratic
+/// reciprocity, without factorising `n`. Any `a` works, including
+/// negative ones and those larger than `n`, since only `a mod n`
+/// matters.
+///
+/// # Panics
+///
+/// If `n` is even.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::jacobi;
+///
+/// assert_eq!(jacobi(2, 7), 1);
+/// assert_eq!(jacobi(3, 7), -1);
+/// assert_eq!(jacobi(-1, 7), -1);
+/// assert_eq!(jacobi(21, 15), 0);
+/// // 2 is not a square modulo 15, but (2/15) = (2/3)(2/5) = 1.
+/// assert_eq!(jacobi(2, 15), 1);
+/// ```
+pub fn jacobi(a: i64, n: u64) -> i8 {
+    assert!(n % 2 == 1, "jacobi: n must be odd, found {}", n);
+
+    let mut a = (a as i128).rem_euclid(n as i128) as u64;
+    let mut n = n;
+    let mut result = 1;
+    while a != 0 {
+        while a % 2 == 0 {
+            a /= 2;
+            // (2/n) = -1 iff n = 3, 5 (mod 8)
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result
+            }
+        }
+        // quadratic reciprocity
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result
+        }
+        let r = n % a;
+        n = a;
+        a = r;
+    }
+    if n == 1 { result } else { 0 }
+}
+
+/// Compute the Legendre symbol (*a*/*p*), for an odd prime `p`.
+///
+/// This is 0 if `p` divides `a`, 1 if `a` is a non-zero square
+/// modulo `p`, and -1 otherwise. It's just `jacobi(a, p)`, which
+/// agrees for primes; `p` isn't checked to be prime.
+///
+/// # Panics
+///
+/// If `p` is even.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::legendre;
+///
+/// // the non-zero squares modulo 7 are 1, 2 and 4.
+/// let symbols = (0..7).map(|a| legendre(a, 7)).collect::<Vec<_>>();
+/// assert_eq!(symbols, [0, 1, 1, -1, 1, -1, -1]);
+/// ```
+pub fn legendre(a: i64, p: u64) -> i8 {
+    jacobi(a, p)
+}
+
+/// Compute a square root of `a` modulo the prime `p`, that is, an `x`
+/// with `x * x = a (mod p)`, or `None` if `a` is not a square modulo
+/// `p`.
+///
+/// Non-squares are rejected with the Legendre symbol, and then the
+/// root is `a^((p + 1)/4)` if `p = 3 (mod 4)`, and is found with the
+/// Tonelli-Shanks algorithm otherwise. Other than 0, every square has
+/// two roots, `x` and `p - x`, and this returns the smaller one. `p`
+/// isn't checked to be prime, and the answer for a composite `p` is
+/// meaningless (but it is still returned in finite time).
+///
+/// # Panics
+///
+/// If `p` is even, other than 2.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::mod_sqrt;
+///
+/// assert_eq!(mod_sqrt(2, 7), Some(3));
+/// assert_eq!(mod_sqrt(3, 7), None);
+/// assert_eq!(mod_sqrt(10, 13), Some(6));
+/// assert_eq!(mod_sqrt(0, 13), Some(0));
+/// ```
+pub fn mod_sqrt(a: u64, p: u64) -> Option<u64> {
+    if p == 2 { return Some(a % 2) }
+
+    let a = a % p;
+    if a == 0 { return Some(0) }
+    // a mod p doesn't always fit in an `i64`, but a - p does then.
+    let signed = if a <= i64::MAX as u64 { a as i64 } else { (a as i128 - p as i128) as i64 };
+    if jacobi(signed, p) != 1 {
+        return None
+    }
+
+    let x = if p % 4 == 3 {
+        // (p + 1)/4, without overflowing.
+        mod_pow(a, p / 4 + 1, p)
+    } else {
+        tonelli_shanks(a, p)?
+    };
+    Some(cmp::min(x, p - x))
+}
+
+/// Find a square root of the quadratic residue `a` modulo the odd
+/// prime `p`, or `None` if `p` turns out not to be prime.
+fn tonelli_shanks(a: u64, p: u64) -> Option<u64> {
+    // p - 1 = q * 2^s, q odd
+    let s = (p - 1).trailing_zeros();
+    let q = (p - 1) >> s;
+
+    // half of the numbers mod p are non-squares, so this is quick.
+    let z = (2..p).find(|&z| jacobi(z as i64, p) == -1)?;
+
+    let mut m = s;
+    let mut c = mod_pow(z, q, p);
+    let mut t = mod_pow(a, q, p);
+    let mut r = mod_pow(a, (q + 1) / 2, p);
+    // invariant: r^2 = a * t, and t has order dividing 2^(m - 1)
+    while t != 1 {
+        // the order of t is 2^i.
+        let mut i = 0;
+        let mut t2 = t;
+        while t2 != 1 {
+            t2 = mod_mul(t2, t2, p);
+            i += 1;
+            if i == m { return None }
+        }
+        let mut b = c;
+        for _ in 0..m - i - 1 {
+            b = mod_mul(b, b, p);
+        }
+        m = i;
+        c = mod_mul(b, b, p);
+        t = mod_mul(t, c, p);
+        r = mod_mul(r, b, p);
+    }
+    Some(r)
+}
+
+/// Find the smallest primitive root modulo the prime `p`, that is,
+/// the smallest generator `g` of the multiplicative group of
+/// integers modulo `p`, or `None` if `p` is not prime.
+///
+/// This factorises `p - 1` with `factor_u64` (trial division by the
+/// primes in `primes`, then Pollard's rho for anything left over, so
+/// it always succeeds), and then `g` is a primitive root exactly when
+/// `g^((p - 1)/q) != 1 (mod p)` for every prime `q` dividing `p - 1`.
+/// Candidates are tried in increasing order, and primitive roots are
+/// common, so only a few are needed.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::{Primes, primitive_root};
+///
+/// let sieve = Primes::sieve(100);
+/// assert_eq!(primitive_root(7, &sieve), Some(3));
+/// assert_eq!(primitive_root(2, &sieve), Some(1));
+/// assert_eq!(primitive_root(1_000_000_007, &sieve), Some(5));
+/// assert_eq!(primitive_root(15, &sieve), None);
+/// ```
+pub fn primitive_root(p: u64, primes: &Primes) -> Option<u64> {
+    match p {
+        2 => return Some(1),
+        3 => return Some(2),
+        _ => {}
+    }
+    if !is_prime_u64(p, Some(primes)) {
+        return None
+    }
+
+    let factors = factor_u64(p - 1, primes);
+    (2..p).find(|&g| factors.iter().all(|&(q, _)| mod_pow(g, (p - 1) / q, p) != 1))
+}
+
+impl Primes {
+    /// Check if `a` is a quadratic residue modulo the prime `p`, that
+    /// is, if `a` is not divisible by `p` and `x * x = a (mod p)` for
+    /// some `x`, or return `None` if `p` is not a prime stored in this
+    /// map.
+    ///
+    /// For odd `p` this is `legendre(a, p) == 1`. Modulo 2, every odd
+    /// `a` is a square (of 1).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// // the non-zero squares modulo 7 are 1, 2 and 4.
+    /// assert_eq!(sieve.is_quadratic_residue(2, 7), Some(true));
+    /// assert_eq!(sieve.is_quadratic_residue(-1, 7), Some(false));
+    /// assert_eq!(sieve.is_quadratic_residue(7, 7), Some(false));
+    /// assert_eq!(sieve.is_quadratic_residue(2, 9), None);
+    /// assert_eq!(sieve.is_quadratic_residue(2, 101), None);
+    /// ```
+    pub fn is_quadratic_residue(&self, a: i64, p: usize) -> Option<bool> {
+        if self.checked_is_prime(p) != Some(true) {
+            return None
+        }
+        Some(if p == 2 { a % 2 != 0 } else { legendre(a, p as u64) == 1 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mod_mul, mod_pow, mod_inverse, jacobi, legendre, mod_sqrt, primitive_root};
+    use Primes;
+    use gcd::gcd;
+
+    const MAX: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
+    #[test]
+    fn mul() {
+        for m in 1..50 {
+            for a in 0..100 {
+                for b in 0..100 {
+                    assert_eq!(mod_mul(a, b, m), a * b % m);
+                }
+            }
+        }
+
+        assert_eq!(mod_mul(MAX, MAX, MAX), 0);
+        assert_eq!(mod_mul(MAX - 1, MAX - 1, MAX), 1);
+        assert_eq!(mod_mul(MAX, MAX, MAX - 1), 1);
+        assert_eq!(mod_mul(1 << 63, 2, MAX), 1);
+        assert_eq!(mod_mul(1 << 32, 1 << 32, (1 << 63) + 1), (1 << 63) - 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mul_zero_modulus() {
+        mod_mul(1, 2, 0);
+    }
+
+    #[test]
+    fn pow() {
+        // agrees with repeated multiplication
+        for m in 1..40 {
+            for base in 0..40 {
+                let mut expected = 1 % m;
+                for exp in 0..70 {
+                    assert_eq!(mod_pow(base, exp, m), expected, "{}^{} mod {}", base, exp, m);
+                    expected = expected * base % m;
+                }
+            }
+        }
+
+        for &m in [2, 3, MAX - 1, MAX, 18_446_744_073_709_551_557, 1 << 63].iter() {
+            assert_eq!(mod_pow(MAX, 0, m), 1);
+            assert_eq!(mod_pow(0, MAX, m), 0);
+            assert_eq!(mod_pow(1, MAX, m), 1);
+            assert_eq!(mod_pow(12345, 1, m), 12345 % m);
+        }
+        assert_eq!(mod_pow(MAX, MAX, 1), 0);
+
+        // -1 to odd and even powers
+        assert_eq!(mod_pow(MAX - 1, MAX, MAX), MAX - 1);
+        assert_eq!(mod_pow(MAX - 1, MAX - 1, MAX), 1);
+        assert_eq!(mod_pow(2, 64, MAX), 1);
+        assert_eq!(mod_pow(2, 63, 1 << 63), 0);
+
+        // Fermat's little theorem for the largest u64 prime.
+        let p = 18_446_744_073_709_551_557;
+        for &a in [2, 3, 1 << 40, MAX, p - 1].iter() {
+            assert_eq!(mod_pow(a, p - 1, p), 1, "{}", a);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn pow_zero_modulus() {
+        mod_pow(2, 0, 0);
+    }
+
+    #[test]
+    fn inverse() {
+        for m in 1..200 {
+            for a in 0..400 {
+                match mod_inverse(a, m) {
+                    Some(x) => {
+                        assert!(x < m);
+                        assert_eq!(mod_mul(a, x, m), 1 % m, "{}^-1 mod {}", a, m);
+                    }
+                    None => assert!(gcd(a, m) != 1, "{}^-1 mod {}", a, m),
+                }
+            }
+        }
+
+        // pseudo-random pairs of large numbers
+        let mut x = 0x1234_5678_9ABC_DEF1u64;
+        let mut next = || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        for _ in 0..10_000 {
+            let (a, m) = (next(), next() | 1);
+            match mod_inverse(a, m) {
+                Some(inv) => assert_eq!(mod_mul(a, inv, m), 1),
+                None => assert!(gcd(a, m) > 1),
+            }
+        }
+
+        let p = 18_446_744_073_709_551_557;
+        // the largest prime below 2^63.
+        let q = (1 << 63) - 25;
+        assert!(::is_prime_u64(q, None));
+        for &m in [p, q, MAX].iter() {
+            for &a in [1, 2, 3, 1 << 62, MAX - 1, p - 1].iter() {
+                if let Some(inv) = mod_inverse(a, m) {
+                    assert_eq!(mod_mul(a, inv, m), 1);
+                } else {
+                    assert!(gcd(a, m) > 1);
+                }
+            }
+            assert_eq!(mod_inverse(0, m), None);
+            assert_eq!(mod_inverse(m, m), None);
+            assert_eq!(mod_inverse(1, m), Some(1));
+            assert_eq!(mod_inverse(m - 1, m), Some(m - 1));
+        }
+        // Fermat's little theorem gives the inverse modulo a prime.
+        assert_eq!(mod_inverse(12345, p), Some(mod_pow(12345, p - 2, p)));
+        // 3 divides u64::MAX
+        assert_eq!(mod_inverse(3, MAX), None);
+        assert_eq!(mod_inverse(0, 1), Some(0));
+        assert_eq!(mod_inverse(MAX, 1), Some(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn inverse_zero_modulus() {
+        mod_inverse(1, 0);
+    }
+
+    #[test]
+    fn jacobi_table() {
+        // a table from Wikipedia, `ROWS[k]` is n = 2k + 1, a = 1..
+        const ROWS: &'static [&'static [i8]] = &[
+            &[1],
+            &[1, -1, 0],
+            &[1, -1, -1, 1, 0],
+            &[1, 1, -1, 1, -1, -1, 0],
+            &[1, 1, 0, 1, 1, 0, 1, 1, 0],
+            &[1, -1, 1, 1, 1, -1, -1, -1, 1, -1, 0],
+            &[1, -1, 1, 1, -1, -1, -1, -1, 1, 1, -1, 1, 0],
+            &[1, 1, 0, 1, 0, 0, -1, 1, 0, 0, -1, 0, -1, -1, 0],
+            ];
+        for (k, row) in ROWS.iter().enumerate() {
+            let n = 2 * k as u64 + 1;
+            for (i, &expected) in row.iter().enumerate() {
+                let a = i as i64 + 1;
+                assert_eq!(jacobi(a, n), expected, "({}/{})", a, n);
+                assert_eq!(jacobi(a + 3 * n as i64, n), expected);
+                assert_eq!(jacobi(a - 5 * n as i64, n), expected);
+            }
+        }
+        assert_eq!(jacobi(0, 1), 1);
+        assert_eq!(jacobi(i64::MIN, MAX), jacobi((i64::MIN as i128).rem_euclid(MAX as i128) as i64, MAX));
+    }
+
+    #[test]
+    fn legendre_euler_criterion() {
+        let primes = Primes::sieve(1000);
+        for p in primes.primes().skip(1) {
+            let p_ = p as u64;
+            for a in -300..300i64 {
+                let power = mod_pow(a.rem_euclid(p as i64) as u64, (p_ - 1) / 2, p_);
+                let expected = if power == p_ - 1 { -1 } else { power as i8 };
+                assert_eq!(legendre(a, p_), expected, "({}/{})", a, p);
+            }
+        }
+    }
+
+    #[test]
+    fn jacobi_multiplicative() {
+        for n in (1..300u64).step_by(2) {
+            for a in -50..50i64 {
+                for b in -50..50i64 {
+                    assert_eq!(jacobi(a * b, n), jacobi(a, n) * jacobi(b, n), "({}*{}/{})", a, b, n);
+                }
+            }
+            // and in the denominator
+            for m in (1..50u64).step_by(2) {
+                assert_eq!(jacobi(7, n * m), jacobi(7, n) * jacobi(7, m));
+            }
+        }
+    }
+
+    #[test]
+    fn quadratic_residues() {
+        let sieve = Primes::sieve(200);
+        for p in sieve.primes() {
+            let mut squares = vec![false; p];
+            for x in 1..p {
+                squares[x * x % p] = true;
+            }
+            for a in -500..500i64 {
+                let expected = squares[a.rem_euclid(p as i64) as usize];
+                assert_eq!(sieve.is_quadratic_residue(a, p), Some(expected), "{} mod {}", a, p);
+            }
+        }
+        for &n in [0, 1, 4, 9, 15, 199 * 199, 211, 1 << 40].iter() {
+            assert_eq!(sieve.is_quadratic_residue(2, n), None, "{}", n);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn jacobi_even() {
+        jacobi(3, 10);
+    }
+
+    #[test]
+    fn sqrt_small() {
+        let primes = Primes::sieve(1000);
+        for p in primes.primes() {
+            let p = p as u64;
+            let mut squares = vec![false; p as usize];
+            for x in 0..p {
+                squares[(x * x % p) as usize] = true;
+            }
+            for a in 0..3 * p {
+                match mod_sqrt(a, p) {
+                    Some(x) => {
+                        assert!(x <= p - x || x == 0, "sqrt({}) mod {} = {}", a, p, x);
+                        assert_eq!(x * x % p, a % p, "sqrt({}) mod {} = {}", a, p, x);
+                    }
+                    None => assert!(!squares[(a % p) as usize], "sqrt({}) mod {}", a, p),
+                }
+            }
+        }
+        assert_eq!(mod_sqrt(MAX, 2), Some(1));
+        assert_eq!(mod_sqrt(0, 2), Some(0));
+    }
+
+    #[test]
+    fn sqrt_random() {
+        use is_prime::{next_prime, prev_prime, XorShift};
+
+        let sieve = Primes::sieve(1 << 20);
+        let primes = sieve.primes().skip(1).map(|p| p as u64).collect::<Vec<_>>();
+        let mut rng = XorShift::new(0);
+        // near 2^63, and the very largest `u64` primes.
+        let mut large = vec![];
+        for &n in [1 << 63, MAX].iter() {
+            let mut below = n;
+            for _ in 0..10 {
+                below = prev_prime(below).unwrap();
+                large.push(below);
+            }
+        }
+        large.push(next_prime(1 << 63).unwrap());
+
+        for i in 0..20_000 {
+            let p = if i % 4 == 0 { large[i / 4 % large.len()] } else { primes[rng.next() as usize % primes.len()] };
+            let x = rng.next() % p;
+            let a = mod_mul(x, x, p);
+            let root = mod_sqrt(a, p).unwrap();
+            // the root is one of x and p - x, and both square to a.
+            assert!(root == x || root == p - x || (x == 0 && root == 0), "sqrt({}) mod {}", a, p);
+            assert_eq!(mod_mul(root, root, p), a);
+            assert_eq!(mod_mul(p - root, p - root, p), a);
+
+            // and only squares have roots.
+            let b = rng.next() % (p - 1) + 1;
+            let is_square = mod_pow(b, (p - 1) / 2, p) == 1;
+            match mod_sqrt(b, p) {
+                Some(r) => {
+                    assert!(is_square, "sqrt({}) mod {}", b, p);
+                    assert_eq!(mod_mul(r, r, p), b);
+                }
+                None => assert!(!is_square, "sqrt({}) mod {}", b, p),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn sqrt_even() {
+        mod_sqrt(3, 10);
+    }
+
+    #[test]
+    fn primitive_roots() {
+        let sieve = Primes::sieve(2000);
+        for &(p, g) in [(2, 1), (3, 2), (5, 2), (7, 3), (11, 2), (13, 2), (23, 5), (41, 6), (761, 6)].iter() {
+            assert_eq!(primitive_root(p, &sieve), Some(g), "{}", p);
+        }
+        for &n in [0, 1, 4, 9, 15, 561, 1 << 40, MAX].iter() {
+            assert_eq!(primitive_root(n, &sieve), None, "{}", n);
+        }
+
+        // the order of g is p - 1, and nothing smaller has that order.
+        let order = |g, p| (1..p).find(|&k| mod_pow(g, k, p) == 1).unwrap();
+        for p in sieve.primes() {
+            let p = p as u64;
+            let g = primitive_root(p, &sieve).unwrap();
+            assert_eq!(order(g, p), p - 1, "{}", p);
+            assert!((1..g).all(|h| order(h, p) < p - 1), "{}", p);
+        }
+    }
+
+    #[test]
+    fn primitive_roots_large() {
+        use pollard_rho::factor_u64;
+
+        let sieve = Primes::sieve(100);
+        // the largest u64 prime, and some other large ones.
+        for &p in [18_446_744_073_709_551_557, 1_000_000_007, 998_244_353, 4_294_967_291, 9_223_372_036_854_775_783].iter() {
+            let g = primitive_root(p, &sieve).unwrap();
+            // g^(p - 1) = 1, but no maximal proper divisor of p - 1 works.
+            assert_eq!(mod_pow(g, p - 1, p), 1);
+            for (q, _) in factor_u64(p - 1, &sieve) {
+                assert!(mod_pow(g, (p - 1) / q, p) != 1, "{} {}", p, q);
+            }
+        }
+        assert_eq!(primitive_root(998_244_353, &sieve), Some(3));
+    }
+}
@@ -0,0 +1,257 @@
+use bit_vec::{BitVec, BITS};
+use std::cmp;
+#[cfg(feature = "rayon")]
+use std::iter;
+use std::thread;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use Primes;
+use float;
+
+// the number of odd numbers in each segment sieved in parallel, a
+// multiple of the block size.
+const SEGMENT_LEN: usize = 1 << 18;
+
+// the number of blocks of bits in each piece `par_primes` splits the
+// sieve into.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_BLOCKS: usize = 1 << 10;
+
+// The base primes, and the length of the bit vector, for sieving up
+// to `limit` in segments.
+fn setup(limit: usize) -> (Primes, usize) {
+    // match `Primes::sieve`.
+    let limit = cmp::max(10, limit);
+    let base = Primes::sieve(float::sqrt(limit as f64) as usize + 1);
+    (base, (limit + 1) / 2)
+}
+
+// Sieve the indices `lo..hi` one cache-sized segment at a time.
+fn sieve_range(base: &Primes, lo: usize, hi: usize) -> BitVec {
+    let mut ret = BitVec::with_capacity(hi - lo);
+    let mut start = lo;
+    while start < hi {
+        let end = cmp::min(hi, start + SEGMENT_LEN);
+        ret.append(&Primes::sieve_segment(base, start, end));
+        start = end;
+    }
+    ret
+}
+
+fn assemble<I: Iterator<Item = BitVec>>(len: usize, segments: I) -> Primes {
+    let mut is_prime = BitVec::with_capacity(len);
+    for segment in segments {
+        is_prime.append(&segment);
+    }
+    Primes::from_bits(is_prime)
+}
+
+impl Primes {
+    /// Construct a `Primes` via a sieve up to at least `limit`,
+    /// sieving segments in parallel with `rayon`.
+    ///
+    /// The primes up to `sqrt(limit)` are found first, and then used
+    /// to sieve each segment of the rest independently, so this gives
+    /// exactly the same result as `Primes::sieve(limit)`.
+    ///
+    /// This requires the `rayon` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "rayon")] {
+    /// let sieve = slow_primes::Primes::par_sieve(1_000_000);
+    /// assert_eq!(sieve.prime_pi(999_999), Some(78498));
+    /// # }
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_sieve(limit: usize) -> Primes {
+        let (base, len) = setup(limit);
+        let count = (len + SEGMENT_LEN - 1) / SEGMENT_LEN;
+
+        let segments = (0..count).into_par_iter()
+            .map(|i| {
+                let lo = i * SEGMENT_LEN;
+                Primes::sieve_segment(&base, lo, cmp::min(len, lo + SEGMENT_LEN))
+            })
+            .collect::<Vec<_>>();
+
+        assemble(len, segments.into_iter())
+    }
+
+    /// A parallel iterator over the primes stored in this sieve, for
+    /// use with `rayon`.
+    ///
+    /// The bits for the odd numbers are split into pieces of whole
+    /// blocks, each of which yields its own primes, so `rayon` can
+    /// work on them independently, and 2 is yielded once, in addition
+    /// to those. The primes are in increasing order when collected,
+    /// as for `self.primes()`.
+    ///
+    /// This requires the `rayon` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate rayon;
+    /// extern crate slow_primes;
+    ///
+    /// # #[cfg(feature = "rayon")]
+    /// fn main() {
+    ///     use rayon::prelude::*;
+    ///
+    ///     let sieve = slow_primes::Primes::sieve(1_000_000);
+    ///     assert_eq!(sieve.par_primes().count(), 78498);
+    ///     assert_eq!(sieve.par_primes().map(|p| p as u64).sum::<u64>(), 37_550_402_023);
+    /// }
+    /// # #[cfg(not(feature = "rayon"))] fn main() {}
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_primes<'a>(&'a self) -> impl ParallelIterator<Item = usize> + 'a {
+        // `sieve` always stores more than 2.
+        let odd = self.bit_blocks()
+            .par_chunks(PAR_CHUNK_BLOCKS)
+            .enumerate()
+            .flat_map_iter(|(i, chunk)| {
+                let first = i * PAR_CHUNK_BLOCKS;
+                chunk.iter().enumerate().flat_map(move |(j, &block)| {
+                    let base = (first + j) * BITS;
+                    let mut block = block;
+                    iter::from_fn(move || {
+                        if block == 0 { return None }
+                        let idx = base + block.trailing_zeros() as usize;
+                        block &= block - 1;
+                        Some(2 * idx + 1)
+                    })
+                })
+            });
+        rayon::iter::once(2).chain(odd)
+    }
+
+    /// Construct a `Primes` via a sieve up to at least `limit`, using
+    /// `threads` threads.
+    ///
+    /// The primes up to `sqrt(limit)` are found on the current
+    /// thread, and then the rest of the range is split into `threads`
+    /// contiguous chunks, each sieved by its own thread. This gives
+    /// exactly the same result as `Primes::sieve(limit)`, and is
+    /// useful for large limits (say, above 10<sup>7</sup>) on
+    /// machines with several cores. A `threads` of 0 is treated as 1.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve_parallel(1_000_000, 4);
+    /// assert_eq!(sieve.prime_pi(999_999), Some(78498));
+    /// ```
+    pub fn sieve_parallel(limit: usize, threads: usize) -> Primes {
+        let (base, len) = setup(limit);
+        let threads = cmp::max(threads, 1);
+        // round chunks up to a whole number of blocks, so that each
+        // one is a disjoint set of words.
+        let chunk = (len + threads - 1) / threads;
+        let chunk = cmp::max((chunk + BITS - 1) / BITS * BITS, BITS);
+
+        let chunks = thread::scope(|scope| {
+            let base = &base;
+            let handles = (0..len).step_by(chunk)
+                .map(|lo| scope.spawn(move || sieve_range(base, lo, cmp::min(len, lo + chunk))))
+                .collect::<Vec<_>>();
+            handles.into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assemble(len, chunks.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+
+    #[test]
+    fn segments() {
+        // the segments agree with the full sieve wherever they're cut.
+        let full = Primes::sieve(100_000).to_bytes();
+        let (base, len) = super::setup(100_000);
+        for &seg in [1, 7, 32, 64, 1000, 1 << 18].iter() {
+            let segments = (0..(len + seg - 1) / seg)
+                .map(|i| Primes::sieve_segment(&base, i * seg, ::std::cmp::min(len, (i + 1) * seg)));
+            assert!(super::assemble(len, segments).to_bytes() == full, "{}", seg);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_sieve() {
+        for &limit in [0, 10, 30, 31, 100, 1000, 1 << 18, (1 << 19) + 1, 3_000_001].iter() {
+            let serial = Primes::sieve(limit);
+            let parallel = Primes::par_sieve(limit);
+            assert_eq!(parallel.upper_bound(), serial.upper_bound());
+            assert!(parallel.to_bytes() == serial.to_bytes(), "{}", limit);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_primes() {
+        use rayon::prelude::*;
+
+        for &limit in [0, 10, 30, 31, 100, 1000, 64 * 128, 64 * 128 + 2, 1 << 18, 3_000_001].iter() {
+            let sieve = Primes::sieve(limit);
+            assert_eq!(sieve.par_primes().count(), sieve.primes().count(), "{}", limit);
+            assert_eq!(sieve.par_primes().sum::<usize>(), sieve.primes().sum::<usize>(), "{}", limit);
+            let collected = sieve.par_primes().collect::<Vec<_>>();
+            assert_eq!(collected, sieve.primes().collect::<Vec<_>>(), "{}", limit);
+        }
+    }
+
+    #[test]
+    fn sieve_parallel() {
+        for &limit in [0, 10, 30, 31, 100, 1000, 1 << 18, (1 << 19) + 1, 3_000_001].iter() {
+            let serial = Primes::sieve(limit);
+            for &threads in [0, 1, 2, 3, 4, 7, 64].iter() {
+                let parallel = Primes::sieve_parallel(limit, threads);
+                assert_eq!(parallel.upper_bound(), serial.upper_bound());
+                assert!(parallel.to_bytes() == serial.to_bytes(), "{} {}", limit, threads);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
+    use Primes;
+
+    #[bench]
+    fn sieve_parallel_huge_1(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_parallel(10_000_000, 1))
+    }
+    #[bench]
+    fn sieve_parallel_huge_4(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_parallel(10_000_000, 4))
+    }
+
+    #[bench]
+    #[cfg(feature = "rayon")]
+    fn par_sieve_huge(b: &mut Bencher) {
+        b.iter(|| Primes::par_sieve(10_000_000))
+    }
+
+    #[bench]
+    #[cfg(feature = "rayon")]
+    fn par_primes_sum_huge(b: &mut Bencher) {
+        use rayon::prelude::*;
+        let sieve = Primes::sieve(100_000_000);
+        b.iter(|| sieve.par_primes().map(|p| p as u64).sum::<u64>())
+    }
+    #[bench]
+    fn primes_sum_huge(b: &mut Bencher) {
+        let sieve = Primes::sieve(100_000_000);
+        b.iter(|| sieve.primes().map(|p| p as u64).sum::<u64>())
+    }
+}
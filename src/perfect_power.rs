@@ -1,20 +1,76 @@
-use num_::Integer;
+use {Primes, float};
 
-fn wrapping_pow(mut base: u64, mut exp: u32) -> u64 {
-    let mut acc: u64 = 1;
-    while exp > 0 {
-        if exp % 2 == 1 {
-            acc = acc.wrapping_mul(base)
+// the primes below 64, the only prime exponents a `u64` can have.
+const EXPONENTS: [u32; 18] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61];
+
+/// Compute the integer `k`th root of `x`, that is, the largest `r`
+/// with `r^k <= x`, for `k >= 1`.
+///
+/// The floating point estimate can be off by one or so for large `x`
+/// (it only has 53 bits of precision), so it is corrected exactly.
+fn iroot(x: u64, k: u32) -> u64 {
+    if k == 1 || x < 2 { return x }
+
+    // whether r^k <= x, without overflowing.
+    let fits = |r: u64| match r.checked_pow(k) {
+        Some(p) => p <= x,
+        None => false
+    };
+    let mut r = float::powf(x as f64, 1.0 / k as f64) as u64;
+    while !fits(r) {
+        r -= 1
+    }
+    while fits(r + 1) {
+        r += 1
+    }
+    r
+}
+
+/// Returns `Some((y, k))` such that `x = y^k` with `k >= 2` as large
+/// as possible, or `None` if `x` is not a perfect power.
+///
+/// `0` and `1` are `0^k` and `1^k` for every `k`, and so have no
+/// largest exponent, and give `None`. Roots are computed exactly with
+/// integers, since floating point alone gives wrong answers for some
+/// large `x`.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::perfect_power;
+///
+/// assert_eq!(perfect_power(64), Some((2, 6)));
+/// assert_eq!(perfect_power(6u64.pow(7)), Some((6, 7)));
+/// assert_eq!(perfect_power(1 << 63), Some((2, 63)));
+/// assert_eq!(perfect_power(12), None);
+/// assert_eq!(perfect_power(1), None);
+/// ```
+pub fn perfect_power(x: u64) -> Option<(u64, u32)> {
+    if x < 2 { return None }
+
+    // if x = y^(ab), then x is an a-th power, and its root is a b-th
+    // power, so peel off prime exponents one at a time.
+    let (mut y, mut k) = (x, 1);
+    let mut i = 0;
+    while i < EXPONENTS.len() && 1 << EXPONENTS[i] <= y {
+        let q = EXPONENTS[i];
+        let r = iroot(y, q);
+        if r.pow(q) == y {
+            y = r;
+            k *= q;
+        } else {
+            i += 1;
         }
-        base = base.wrapping_mul(base);
-        exp /= 2;
     }
-    acc
+    if k > 1 { Some((y, k)) } else { None }
 }
 
 /// Returns integers `(y, k)` such that `x = y^k` with `k` maximised
 /// (other than for `x = 0, 1`, in which case `y = x`, `k = 1`).
 ///
+/// See `perfect_power` for a version that distinguishes numbers that
+/// aren't perfect powers.
+///
 /// # Examples
 ///
 /// ```rust
@@ -28,37 +84,7 @@ fn wrapping_pow(mut base: u64, mut exp: u32) -> u64 {
 /// assert_eq!(slow_primes::as_perfect_power(15), (15, 1));
 /// ```
 pub fn as_perfect_power(x: u64) -> (u64, u8) {
-    if x == 0 || x == 1 {
-        return (x, 1)
-    }
-
-    let floor_log_2 = 64 - x.leading_zeros() as u32 - 1;
-
-    let x_ = x as f64;
-    let mut last = (x, 1);
-    // TODO: we could be smarter about this: we know all the possible
-    // primes that can divide the exponent (since we have a list up to
-    // 251 >= 64), so we really only need to check them.
-    let mut expn: u32 = 2;
-    let mut step = 1;
-    while expn <= floor_log_2 {
-        let factor = x_.powf(1.0/expn as f64).round() as u64;
-        // the only case this will wrap is if x is close to 2^64 and
-        // the round() rounds up, pushing this calculation over the
-        // edge, however, the overflow will be well away from x, so we
-        // still correctly don't take this branch. (x can't be a
-        // perfect power if the result rounds away.)
-        if wrapping_pow(factor, expn) == x {
-            last = (factor, expn as u8);
-            // if x is a 2nd and 5th power, it's going to be a 10th
-            // power too, meaning we can search faster.
-            // TODO: check if this is actually saving work
-            step = step.lcm(&expn);
-        }
-
-        expn += step;
-    }
-    last
+    perfect_power(x).map_or((x, 1), |(y, k)| (y, k as u8))
 }
 
 /// Return `Some((p, k))` if `x = p^k` for some prime `p` and `k >= 1`
@@ -74,12 +100,65 @@ pub fn as_prime_power(x: u64) -> Option<(u64, u8)> {
     }
 }
 
+impl Primes {
+    /// Return `Some((p, k))` if `n = p^k` for some prime `p` and `k >=
+    /// 1` (that is, including when `n` is itself a prime), and `None`
+    /// otherwise.
+    ///
+    /// This only trial divides up to the smallest prime factor of `n`:
+    /// `n` is a prime power exactly when dividing out all the copies
+    /// of that prime leaves 1. If `n` has no prime factors in the
+    /// sieve, this falls back to the free function `as_prime_power`,
+    /// so there is never an error, even for `n` too large to
+    /// `factor`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.as_prime_power(8), Some((2, 3)));
+    /// assert_eq!(sieve.as_prime_power(97), Some((97, 1)));
+    /// assert_eq!(sieve.as_prime_power(12), None);
+    /// assert_eq!(sieve.as_prime_power(1), None);
+    /// // 1_000_003 is outside the sieve.
+    /// assert_eq!(sieve.as_prime_power(1_000_003 * 1_000_003), Some((1_000_003, 2)));
+    /// ```
+    pub fn as_prime_power(&self, n: usize) -> Option<(usize, u32)> {
+        if n < 2 { return None }
+
+        let mut smallest = None;
+        match self.factor_with(n, |p, k| { smallest = Some((p, k as u32)); false }) {
+            Ok(()) => smallest.and_then(|(p, k)| if p.pow(k) == n { Some((p, k)) } else { None }),
+            // nothing in the sieve divides n.
+            Err(_) => as_prime_power(n as u64).map(|(p, k)| (p as usize, k as u32))
+        }
+    }
+
+    /// Check if `n = p^k` for some prime `p` and `k >= 1`.
+    ///
+    /// See `as_prime_power`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert!(sieve.is_prime_power(81));
+    /// assert!(sieve.is_prime_power(2));
+    /// assert!(!sieve.is_prime_power(1));
+    /// assert!(!sieve.is_prime_power(36));
+    /// ```
+    pub fn is_prime_power(&self, n: usize) -> bool {
+        self.as_prime_power(n).is_some()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::num::{Float, Int};
     use Primes;
 
-    use super::{as_perfect_power, as_prime_power};
+    use super::{as_perfect_power, as_prime_power, perfect_power, iroot};
 
     #[test]
     fn perfect_and_prime_power() {
@@ -104,24 +183,21 @@ mod tests {
         for &(x, expected, is_prime) in tests.iter() {
             assert_eq!(as_perfect_power(x), expected);
             assert_eq!(as_prime_power(x),
-                       if is_prime { Some((expected))} else { None })
+                       if is_prime { Some(expected) } else { None })
         }
 
         let sieve = Primes::sieve(200);
         let mut primes = sieve.primes();
         const MAX: f64 = 0xFFFF_FFFF_FFFF_FFFFu64 as f64;
         // test a whole pile of (semi)primes
-        loop {
-            let p = match primes.next() {
-                Some(p) => p as u64,
-                None => break
-            };
+        while let Some(p) = primes.next() {
+            let p = p as u64;
 
             let subprimes = primes.clone().map(|x| (x, false));
             // include 1 to test p itself.
             for (q, is_prime) in Some((1, true)).into_iter().chain(subprimes) {
                 let pq = p * q as u64;
-                for n in (1..MAX.log(pq as f64) as u32) {
+                for n in 1..MAX.log(pq as f64) as u32 {
                     let x = pq.pow(n);
 
                     let expected = (pq, n as u8);
@@ -132,4 +208,92 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn integer_root() {
+        for x in 0..10_000u64 {
+            for k in 1..15 {
+                let r = iroot(x, k);
+                assert!(r.pow(k) <= x && (r + 1).pow(k) > x, "{} {}", x, k);
+            }
+        }
+        let max = 0xFFFF_FFFF_FFFF_FFFF;
+        assert_eq!(iroot(max, 2), 0xFFFF_FFFF);
+        assert_eq!(iroot(max, 3), 2_642_245);
+        assert_eq!(iroot(max, 63), 2);
+        assert_eq!(iroot(max, 64), 1);
+        // 2^53 + 1 can't be represented exactly as an f64.
+        let r = (1 << 26) + 1;
+        assert_eq!(iroot(r * r, 2), r);
+        assert_eq!(iroot(r * r - 1, 2), r - 1);
+    }
+
+    #[test]
+    fn perfect_powers() {
+        const LIMIT: usize = 1_000_000;
+        // the largest exponent of each number, by brute force.
+        let mut expected = vec![None; LIMIT];
+        for y in 2..1000u64 {
+            let mut x = y * y;
+            let mut k = 2;
+            while x < LIMIT as u64 {
+                match expected[x as usize] {
+                    Some((_, j)) if j >= k => {}
+                    _ => expected[x as usize] = Some((y, k))
+                }
+                x *= y;
+                k += 1;
+            }
+        }
+        for (x, &e) in expected.iter().enumerate() {
+            assert_eq!(perfect_power(x as u64), e, "{}", x);
+        }
+
+        let max = 0xFFFF_FFFF_FFFF_FFFF;
+        let tests = [
+            (0, None),
+            (1, None),
+            (1 << 63, Some((2, 63))),
+            ((1 << 63) - 1, None),
+            (279_936, Some((6, 7))),
+            (6u64.pow(24), Some((6, 24))),
+            (3u64.pow(40), Some((3, 40))),
+            (3u64.pow(33), Some((3, 33))),
+            (10u64.pow(18), Some((10, 18))),
+            (0xFFFF_FFFF * 0xFFFF_FFFF, Some((0xFFFF_FFFF, 2))),
+            (0xFFFF_FFFF * 0xFFFF_FFFF + 1, None),
+            (0xFFFF_FFFF * 0xFFFF_FFFF - 1, None),
+            (2_642_245u64.pow(3), Some((2_642_245, 3))),
+            (1_000_003 * 1_000_003 * 1_000_003, Some((1_000_003, 3))),
+            (max, None),
+            ];
+        for &(x, e) in tests.iter() {
+            assert_eq!(perfect_power(x), e, "{}", x);
+        }
+    }
+
+    #[test]
+    fn sieve_prime_power() {
+        let sieve = Primes::sieve(1000);
+        assert_eq!(sieve.as_prime_power(8), Some((2, 3)));
+        assert_eq!(sieve.as_prime_power(12), None);
+        assert_eq!(sieve.as_prime_power(0), None);
+
+        for n in 0..100_000 {
+            let expected = sieve.factor(n).ok()
+                .and_then(|f| if f.len() == 1 { Some((f[0].0, f[0].1 as u32)) } else { None });
+            assert_eq!(sieve.as_prime_power(n), expected, "{}", n);
+            assert_eq!(sieve.is_prime_power(n), expected.is_some());
+        }
+
+        // numbers `factor` can't handle.
+        let small = Primes::sieve(30);
+        let (p, q) = (1_000_003, 1_000_033);
+        assert_eq!(small.as_prime_power(p * p * p), Some((p, 3)));
+        assert_eq!(small.as_prime_power(p * q), None);
+        assert_eq!(small.as_prime_power(2 * p * p), None);
+        assert_eq!(small.as_prime_power(1 << 63), Some((2, 63)));
+        assert_eq!(small.as_prime_power(18_446_744_073_709_551_557), Some((18_446_744_073_709_551_557, 1)));
+        assert!(!small.is_prime_power(0xFFFF_FFFF_FFFF_FFFF));
+    }
 }
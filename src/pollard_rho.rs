@@ -0,0 +1,206 @@
+use std::cmp;
+use std::vec::Vec;
+
+use num_::Integer;
+
+use Primes;
+use modular::mod_mul;
+
+// how many steps to take between each gcd in Brent's variant.
+const STEPS: u64 = 128;
+
+fn abs_diff(x: u64, y: u64) -> u64 {
+    x.abs_diff(y)
+}
+
+/// Find a non-trivial factor of `n` using Pollard's rho algorithm
+/// (Brent's variant), where `n` must be an odd composite number.
+///
+/// This uses the sequence x ↦ x<sup>2</sup> + *c* (mod `n`), trying
+/// successive values of *c* until a factor is found.
+pub fn pollard_rho(n: u64) -> u64 {
+    debug_assert!(n % 2 == 1 && n > 1);
+
+    let mut c = 1;
+    loop {
+        let f = |x: u64| {
+            // x^2 < n, and so can't overflow when adding c < n.
+            let y = mod_mul(x, x, n);
+            if y >= n - c { y - (n - c) } else { y + c }
+        };
+
+        let mut y = 2;
+        let mut x = y;
+        let mut ys = y;
+        let mut q = 1;
+        let mut g = 1;
+        let mut r = 1;
+        while g == 1 {
+            x = y;
+            for _ in 0..r {
+                y = f(y)
+            }
+            let mut k = 0;
+            while k < r && g == 1 {
+                ys = y;
+                for _ in 0..cmp::min(STEPS, r - k) {
+                    y = f(y);
+                    q = mod_mul(q, abs_diff(x, y), n);
+                }
+                g = q.gcd(&n);
+                k += STEPS;
+            }
+            r *= 2;
+        }
+
+        if g == n {
+            // the batching of the gcds overshot, so step through one
+            // at a time.
+            loop {
+                ys = f(ys);
+                g = abs_diff(x, ys).gcd(&n);
+                if g > 1 { break }
+            }
+        }
+        if g != n {
+            return g
+        }
+        c += 1;
+    }
+}
+
+/// Push all the prime factors of `n` (with multiplicity, in no
+/// particular order) onto `out`.
+pub fn split(n: u64, out: &mut Vec<u64>) {
+    if n == 1 {
+        return
+    }
+    if ::is_prime_miller_rabin(n) {
+        out.push(n);
+        return
+    }
+
+    let d = if n % 2 == 0 { 2 } else { pollard_rho(n) };
+    split(d, out);
+    split(n / d, out);
+}
+
+/// Factorise any non-zero `u64` into (prime, exponent) pairs, sorted
+/// by prime.
+///
+/// Small factors are found by trial division with the primes stored
+/// in `primes`, and the rest with Pollard's rho algorithm, so, unlike
+/// `Primes::factor`, this works even on targets where `usize` is
+/// narrower than `u64`.
+///
+/// # Panics
+///
+/// If `n` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::{Primes, factor_u64};
+///
+/// let sieve = Primes::sieve(100);
+/// assert_eq!(factor_u64(360, &sieve), [(2, 3), (3, 2), (5, 1)]);
+/// assert_eq!(factor_u64(18_446_744_073_709_551_615, &sieve),
+///            [(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]);
+/// ```
+pub fn factor_u64(mut n: u64, primes: &Primes) -> Vec<(u64, u32)> {
+    assert!(n != 0, "factor_u64: cannot factorise zero");
+
+    let mut ret = vec![];
+    for p in primes.primes() {
+        let p = p as u64;
+        if p > n / p { break }
+
+        let mut count = 0;
+        while n % p == 0 {
+            n /= p;
+            count += 1;
+        }
+        if count > 0 {
+            ret.push((p, count))
+        }
+    }
+
+    // everything left is larger than the primes removed so far.
+    let mut large = vec![];
+    split(n, &mut large);
+    large.sort();
+    for p in large {
+        match ret.last_mut() {
+            Some(&mut (q, ref mut k)) if q == p => {
+                *k += 1;
+                continue
+            }
+            _ => {}
+        }
+        ret.push((p, 1))
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+    use super::{factor_u64, pollard_rho, split};
+
+    #[test]
+    fn rho() {
+        let tests = [
+            15,
+            91,
+            101 * 103,
+            7561 * 7561,
+            1_000_000_007 * 998_244_353,
+            4_294_967_291 * 4_294_967_279,
+            ];
+        for &n in tests.iter() {
+            let d = pollard_rho(n);
+            assert!(1 < d && d < n && n % d == 0, "{} is not a factor of {}", d, n);
+        }
+    }
+
+    #[test]
+    fn split_all() {
+        let mut out = vec![];
+        split(2 * 3 * 3 * 1_000_003 * 1_000_003, &mut out);
+        out.sort();
+        assert_eq!(out, [2, 3, 3, 1_000_003, 1_000_003]);
+    }
+
+    #[test]
+    fn u64_factors() {
+        let tiny = Primes::sieve(10);
+        let long = Primes::sieve(1000);
+        for n in 1..100_000 {
+            let expected = long.factor(n).unwrap().iter()
+                .map(|&(p, k)| (p as u64, k as u32))
+                .collect::<Vec<_>>();
+            assert_eq!(factor_u64(n as u64, &tiny), expected);
+            assert_eq!(factor_u64(n as u64, &long), expected);
+        }
+
+        let tests: &[(u64, &[(u64, u32)])] = &[
+            (1 << 63, &[(2, 63)]),
+            (0xFFFF_FFFF_FFFF_FFC5, &[(0xFFFF_FFFF_FFFF_FFC5, 1)]),
+            (0xFFFF_FFFF_FFFF_FFFF,
+             &[(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]),
+            (4_294_967_291 * 4_294_967_279, &[(4_294_967_279, 1), (4_294_967_291, 1)]),
+            (3 * 7 * 7 * 100_003 * 100_003 * 100_003,
+             &[(3, 1), (7, 2), (100_003, 3)]),
+            ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(factor_u64(n, &tiny), expected);
+            assert_eq!(factor_u64(n, &long), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn u64_factors_zero() {
+        factor_u64(0, &Primes::sieve(10));
+    }
+}
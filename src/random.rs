@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use is_prime::{is_prime_u64, next_prime};
+
+/// Generate a random prime with exactly `bits` bits, that is, in
+/// `2^(bits - 1)..2^bits`.
+///
+/// This draws random odd numbers with the top bit set until one is
+/// prime (checked with the deterministic Miller-Rabin test). Since
+/// only odd numbers are drawn, 2 is never returned, and so `bits = 2`
+/// always gives 3.
+///
+/// This requires the `rand` feature. The primes are fine for
+/// experiments, but this is not a vetted implementation for real
+/// cryptography.
+///
+/// # Panics
+///
+/// If `bits` is less than 2 (there are no primes with fewer bits)
+/// or more than 64.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate rand;
+/// extern crate slow_primes;
+/// use rand::SeedableRng;
+///
+/// # #[cfg(feature = "rand")]
+/// fn main() {
+///     let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+///     let p = slow_primes::gen_prime(&mut rng, 32);
+///     assert!(1 << 31 <= p && p < 1 << 32);
+///     assert!(slow_primes::is_prime_u64(p, None));
+/// }
+/// # #[cfg(not(feature = "rand"))] fn main() {}
+/// ```
+pub fn gen_prime<R: Rng + ?Sized>(rng: &mut R, bits: u32) -> u64 {
+    assert!((2..65).contains(&bits),
+            "gen_prime: bits must be between 2 and 64, found {}", bits);
+    let top = 1 << (bits - 1);
+    let mask = if bits == 64 { !0 } else { (1 << bits) - 1 };
+    loop {
+        let candidate = (rng.gen::<u64>() & mask) | top | 1;
+        if is_prime_u64(candidate, None) {
+            return candidate
+        }
+    }
+}
+
+/// Generate a random prime in `range`, uniformly among the primes
+/// it contains, or `None` if it contains no primes.
+///
+/// This draws random numbers in `range` until one is prime, after
+/// first finding the smallest prime in `range` with `next_prime`, so
+/// a range without any primes (including empty ranges, and those
+/// below 2) is detected exactly, rather than looping forever.
+///
+/// This requires the `rand` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate rand;
+/// extern crate slow_primes;
+/// use rand::SeedableRng;
+///
+/// # #[cfg(feature = "rand")]
+/// fn main() {
+///     use slow_primes::gen_prime_in_range;
+///
+///     let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+///     let p = gen_prime_in_range(&mut rng, 1000..2000).unwrap();
+///     assert!(1000 <= p && p < 2000);
+///
+///     assert_eq!(gen_prime_in_range(&mut rng, 0..2), None);
+///     // 24, ..., 28 are all composite.
+///     assert_eq!(gen_prime_in_range(&mut rng, 24..29), None);
+/// }
+/// # #[cfg(not(feature = "rand"))] fn main() {}
+/// ```
+pub fn gen_prime_in_range<R: Rng + ?Sized>(rng: &mut R, range: Range<u64>) -> Option<u64> {
+    // the smallest prime at least `range.start`.
+    let first = next_prime(range.start.saturating_sub(1))?;
+    if first >= range.end {
+        return None
+    }
+    // don't go looking for a needle in a haystack.
+    match next_prime(first) {
+        Some(p) if p < range.end => {}
+        _ => return Some(first)
+    }
+    loop {
+        let candidate = rng.gen_range(range.clone());
+        if is_prime_u64(candidate, None) {
+            return Some(candidate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use Primes;
+    use is_prime::is_prime_bpsw;
+    use super::{gen_prime, gen_prime_in_range};
+
+    #[test]
+    fn gen_prime_bits() {
+        let sieve = Primes::sieve(1 << 20);
+        let mut rng = StdRng::seed_from_u64(0);
+        for bits in 2..65 {
+            for _ in 0..20 {
+                let p = gen_prime(&mut rng, bits);
+                assert_eq!(64 - p.leading_zeros(), bits, "{}", p);
+                if p <= sieve.upper_bound() as u64 {
+                    assert!(sieve.is_prime(p as usize), "{}", p);
+                }
+                assert!(is_prime_bpsw(p), "{}", p);
+            }
+        }
+        assert_eq!(gen_prime(&mut rng, 2), 3);
+
+        // every 4- and 5-bit prime shows up.
+        for &(bits, expected) in [(4, &[11, 13][..]), (5, &[17, 19, 23, 29, 31][..])].iter() {
+            let mut seen = (0..200).map(|_| gen_prime(&mut rng, bits)).collect::<Vec<_>>();
+            seen.sort();
+            seen.dedup();
+            assert_eq!(seen, expected);
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let a = (0..10).map(|_| gen_prime(&mut StdRng::seed_from_u64(42), 40)).collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(42);
+        let first = gen_prime(&mut rng, 40);
+        assert!(a.iter().all(|&p| p == first));
+
+        let mut x = StdRng::seed_from_u64(7);
+        let mut y = StdRng::seed_from_u64(7);
+        for _ in 0..10 {
+            assert_eq!(gen_prime_in_range(&mut x, 0..1 << 50), gen_prime_in_range(&mut y, 0..1 << 50));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn gen_prime_one_bit() {
+        gen_prime(&mut StdRng::seed_from_u64(0), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn gen_prime_too_many_bits() {
+        gen_prime(&mut StdRng::seed_from_u64(0), 65);
+    }
+
+    #[test]
+    fn in_range() {
+        let sieve = Primes::sieve(1000);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for &(lo, hi) in [(0, 1000), (2, 3), (100, 200), (500, 520), (0, 100)].iter() {
+            let expected = sieve.primes().filter(|&p| lo <= p && p < hi).collect::<Vec<_>>();
+            let mut seen = (0..500)
+                .map(|_| gen_prime_in_range(&mut rng, lo as u64..hi as u64).unwrap() as usize)
+                .collect::<Vec<_>>();
+            assert!(seen.iter().all(|p| expected.contains(p)));
+            if expected.len() <= 25 {
+                seen.sort();
+                seen.dedup();
+                assert_eq!(seen, expected, "{}..{}", lo, hi);
+            }
+        }
+
+        // no primes
+        for &(lo, hi) in [(0, 0), (0, 2), (1, 2), (5, 5), (24, 29), (8, 9), (9, 3)].iter() {
+            assert_eq!(gen_prime_in_range(&mut rng, lo..hi), None, "{}..{}", lo, hi);
+        }
+        // a single prime
+        assert_eq!(gen_prime_in_range(&mut rng, 7..8), Some(7));
+        assert_eq!(gen_prime_in_range(&mut rng, 24..30), Some(29));
+        assert_eq!(gen_prime_in_range(&mut rng, 0..3), Some(2));
+
+        let max = 0xFFFF_FFFF_FFFF_FFFF;
+        assert_eq!(gen_prime_in_range(&mut rng, max - 60..max), Some(18_446_744_073_709_551_557));
+        let p = gen_prime_in_range(&mut rng, 1 << 63..max).unwrap();
+        assert!(p >= 1 << 63 && is_prime_bpsw(p));
+    }
+}
@@ -0,0 +1,423 @@
+use std::error::Error;
+use std::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+use std::vec::Vec;
+
+use Primes;
+
+const MAGIC: &'static [u8; 4] = b"SPRM";
+const VERSION: u8 = 1;
+// magic, version, 3 reserved bytes, upper bound.
+const HEADER_LEN: usize = 16;
+
+/// The reasons `Primes::from_bytes` can reject its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The data doesn't start with the magic bytes `SPRM`.
+    BadMagic,
+    /// The data was written in an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The data is a different length to that implied by the
+    /// header (for example, because it was truncated).
+    WrongLength {
+        /// The length implied by the header.
+        expected: usize,
+        /// The actual length.
+        found: usize,
+    },
+    /// The data is the right shape, but doesn't describe a sieve.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::BadMagic => f.write_str("missing magic bytes"),
+            ParseError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            ParseError::WrongLength { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            ParseError::Invalid(why) => write!(f, "invalid sieve: {}", why),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl Primes {
+    /// Serialise this sieve into a self-contained sequence of bytes,
+    /// which can be loaded again with `Primes::from_bytes`.
+    ///
+    /// The format is a 16 byte header: the magic bytes `SPRM`, a
+    /// version byte (currently 1), 3 zero bytes, and the upper bound
+    /// as a little-endian `u64`. This is followed by the bits for the
+    /// odd numbers, packed as little-endian words, so bit `i` of the
+    /// data (bit `i % 8` of byte `i / 8`) is set if `2 * i + 1` is
+    /// prime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::Primes;
+    ///
+    /// let sieve = Primes::sieve(1000);
+    /// let bytes = sieve.to_bytes();
+    ///
+    /// let loaded = Primes::from_bytes(&bytes).unwrap();
+    /// assert_eq!(loaded.upper_bound(), sieve.upper_bound());
+    /// assert!(loaded.primes().eq(sieve.primes()));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bits = self.to_bit_bytes();
+        let mut ret = Vec::with_capacity(HEADER_LEN + bits.len());
+        ret.extend(MAGIC.iter().cloned());
+        ret.extend([VERSION, 0, 0, 0].iter().cloned());
+        ret.extend((self.upper_bound() as u64).to_le_bytes().iter().cloned());
+        // `to_bit_bytes` puts the first bit in the most significant
+        // position.
+        ret.extend(bits.into_iter().map(u8::reverse_bits));
+        ret
+    }
+
+    /// Load a sieve from bytes created by `Primes::to_bytes`.
+    ///
+    /// This checks the header and that the data is consistent with
+    /// it, and so returns an `Err` rather than a sieve that might
+    /// misbehave later if the bytes are truncated or corrupted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Primes, ParseError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(if bytes.len() >= 4 && &bytes[..4] != MAGIC {
+                ParseError::BadMagic
+            } else {
+                ParseError::WrongLength { expected: HEADER_LEN, found: bytes.len() }
+            })
+        }
+        let (header, data) = bytes.split_at(HEADER_LEN);
+        let upper_bound = parse_header(header)?;
+        if let Some(expected) = data_len(upper_bound) {
+            if data.len() != expected {
+                return Err(ParseError::WrongLength { expected: HEADER_LEN + expected,
+                                                     found: bytes.len() })
+            }
+        }
+        from_data(upper_bound, data)
+    }
+
+    /// The bits for the odd numbers stored in this sieve, exactly as
+    /// they're stored, without copying.
+    ///
+    /// Bit `i` of the data (bit `i % 64` of word `i / 64`) is set if
+    /// `2 * i + 1` is prime, for `2 * i + 1` up to the upper bound, and
+    /// every bit after that is zero. 2 isn't stored at all, since it
+    /// is the only even prime. This can be loaded again with
+    /// `Primes::from_raw_bits`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(30);
+    /// assert_eq!(sieve.upper_bound(), 29);
+    ///
+    /// // 3, 5, 7, 11, 13, 17, 19, 23, 29
+    /// assert_eq!(sieve.as_raw_bits(), [0b0100_1011_0110_1110]);
+    /// ```
+    pub fn as_raw_bits(&self) -> &[u64] {
+        self.bit_blocks()
+    }
+
+    /// Create a sieve with the given upper bound from the bits for the
+    /// odd numbers, laid out as by `Primes::as_raw_bits`.
+    ///
+    /// The bits aren't checked to be correct (so `bits` could come
+    /// from some other sieving code), but they are checked to be
+    /// consistent with `upper_bound`, which must be odd and at least
+    /// 9, like the upper bound of any sieve created by
+    /// `Primes::sieve`. `bits` must have exactly enough words to store
+    /// the odd numbers up to `upper_bound`, else this returns
+    /// `Err(ParseError::WrongLength { .. })` (counting words, rather
+    /// than bytes), and 1 and the numbers past `upper_bound` must not
+    /// be marked as prime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::{Primes, ParseError};
+    ///
+    /// let sieve = Primes::sieve(1000);
+    /// let bits = sieve.as_raw_bits().to_vec();
+    ///
+    /// let loaded = Primes::from_raw_bits(sieve.upper_bound(), &bits).unwrap();
+    /// assert!(loaded.primes().eq(sieve.primes()));
+    ///
+    /// assert_eq!(Primes::from_raw_bits(sieve.upper_bound(), &bits[1..]).err(),
+    ///            Some(ParseError::WrongLength { expected: bits.len(), found: bits.len() - 1 }));
+    /// ```
+    pub fn from_raw_bits(upper_bound: usize, bits: &[u64]) -> Result<Primes, ParseError> {
+        // the odd numbers 1, 3, ..., upper_bound, without overflow.
+        let expected = (upper_bound / 2 + 1 + 63) / 64;
+        if bits.len() != expected {
+            return Err(ParseError::WrongLength { expected: expected, found: bits.len() })
+        }
+        Primes::from_bit_blocks(upper_bound as u64, bits).map_err(ParseError::Invalid)
+    }
+
+    /// Write this sieve to `w`, in the format of `Primes::to_bytes`.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::Primes;
+    ///
+    /// let sieve = Primes::sieve(1000);
+    /// let mut file = vec![];
+    /// sieve.write_to(&mut file).unwrap();
+    ///
+    /// let loaded = Primes::read_from(&file[..]).unwrap();
+    /// assert_eq!(loaded.upper_bound(), sieve.upper_bound());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(&self.to_bytes())
+    }
+
+    /// Read a sieve written by `Primes::write_to` from `r`.
+    ///
+    /// This reads exactly as many bytes as were written, and returns
+    /// an error of kind `UnexpectedEof` if the input is truncated, or
+    /// `InvalidData` (wrapping a `ParseError`) if it is corrupt.
+    ///
+    /// This requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Primes> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+
+        let mut header = [0; HEADER_LEN];
+        r.read_exact(&mut header)?;
+        let upper_bound = parse_header(&header).map_err(invalid)?;
+        let len = data_len(upper_bound)
+            .ok_or_else(|| invalid(ParseError::Invalid("upper bound too large for this platform")))?;
+
+        // don't trust the length enough to allocate it all up front.
+        let mut data = vec![];
+        r.take(len as u64).read_to_end(&mut data)?;
+        if data.len() != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                      ParseError::WrongLength { expected: HEADER_LEN + len,
+                                                                found: HEADER_LEN + data.len() }))
+        }
+        from_data(upper_bound, &data).map_err(invalid)
+    }
+}
+
+// Check the header, returning the upper bound it stores.
+fn parse_header(header: &[u8]) -> Result<u64, ParseError> {
+    if &header[..4] != MAGIC {
+        return Err(ParseError::BadMagic)
+    }
+    if header[4] != VERSION {
+        return Err(ParseError::UnsupportedVersion(header[4]))
+    }
+    if header[5..8] != [0, 0, 0] {
+        return Err(ParseError::Invalid("reserved bytes are not zero"))
+    }
+
+    let mut ub = [0; 8];
+    ub.copy_from_slice(&header[8..HEADER_LEN]);
+    Ok(u64::from_le_bytes(ub))
+}
+
+// The number of bytes of bit data for a sieve with this upper bound,
+// if it could be stored.
+fn data_len(upper_bound: u64) -> Option<usize> {
+    if upper_bound < usize::MAX as u64 {
+        Some(((upper_bound as usize + 1) / 2 + 7) / 8)
+    } else {
+        None
+    }
+}
+
+fn from_data(upper_bound: u64, data: &[u8]) -> Result<Primes, ParseError> {
+    // `to_bit_bytes` puts the first bit in the most significant
+    // position.
+    let bits = data.iter().map(|&b| b.reverse_bits()).collect::<Vec<_>>();
+    Primes::from_bit_bytes(upper_bound, &bits).map_err(ParseError::Invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::io::{self, Cursor, Read};
+
+    use Primes;
+    use super::ParseError;
+
+    #[test]
+    fn round_trip() {
+        for &limit in [0, 10, 30, 31, 63, 64, 65, 1000, 100_001].iter() {
+            let primes = Primes::sieve(limit);
+            let bytes = primes.to_bytes();
+            let back = Primes::from_bytes(&bytes).unwrap();
+            assert_eq!(back.upper_bound(), primes.upper_bound());
+            assert!(back.primes().eq(primes.primes()));
+            assert_eq!(back, primes);
+            assert_eq!(back.to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn io_round_trip() {
+        for &limit in [0, 10, 31, 63, 64, 1000, 1_000_001].iter() {
+            let primes = Primes::sieve(limit);
+            let mut file = vec![];
+            primes.write_to(&mut file).unwrap();
+            assert_eq!(file, primes.to_bytes());
+
+            // with trailing data, which shouldn't be consumed.
+            file.extend(b"trailing".iter().cloned());
+            let mut cursor = Cursor::new(&file[..]);
+            let back = Primes::read_from(&mut cursor).unwrap();
+            assert_eq!(back.upper_bound(), primes.upper_bound());
+            assert_eq!(back.primes().count(), primes.primes().count());
+            for n in (0..back.upper_bound()).step_by(7) {
+                assert_eq!(back.is_prime(n), primes.is_prime(n));
+            }
+            let mut rest = vec![];
+            cursor.read_to_end(&mut rest).unwrap();
+            assert_eq!(rest, b"trailing");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn io_invalid() {
+        let good = Primes::sieve(100).to_bytes();
+        for len in 0..good.len() {
+            let err = Primes::read_from(&good[..len]).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", len);
+        }
+
+        let mut bad = good.clone();
+        bad[0] = b'X';
+        let err = Primes::read_from(&bad[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "missing magic bytes");
+
+        let mut bad = good.clone();
+        bad[4] = 2;
+        let err = Primes::read_from(&bad[..]).err().unwrap();
+        assert_eq!(err.to_string(), "unsupported format version 2");
+
+        // a huge upper bound doesn't allocate huge amounts of memory.
+        let mut bad = good.clone();
+        bad[8..16].copy_from_slice(&0x0FFF_FFFF_FFFF_FFFFu64.to_le_bytes());
+        assert!(Primes::read_from(&bad[..]).is_err());
+    }
+
+    #[test]
+    fn format() {
+        let bytes = Primes::sieve(30).to_bytes();
+        assert_eq!(bytes,
+                   [b'S', b'P', b'R', b'M', 1, 0, 0, 0,
+                    29, 0, 0, 0, 0, 0, 0, 0,
+                    // 3, 5, 7, 11, 13
+                    0b0110_1110,
+                    // 17, 19, 23, 29
+                    0b0100_1011]);
+    }
+
+    #[test]
+    fn raw_bits() {
+        for &limit in [0, 10, 30, 31, 127, 128, 129, 130, 1000, 100_001].iter() {
+            let primes = Primes::sieve(limit);
+            let bits = primes.as_raw_bits();
+            assert_eq!(bits.len(), (primes.upper_bound() + 1 + 127) / 128);
+            for (i, &word) in bits.iter().enumerate() {
+                for j in 0..64 {
+                    let n = 2 * (64 * i + j) + 1;
+                    let expected = n <= primes.upper_bound() && primes.is_prime(n);
+                    assert_eq!(word & (1 << j) != 0, expected, "{}", n);
+                }
+            }
+
+            let back = Primes::from_raw_bits(primes.upper_bound(), bits).unwrap();
+            assert_eq!(back.upper_bound(), primes.upper_bound());
+            assert_eq!(back.count_primes(), primes.count_primes());
+            assert_eq!(back, primes);
+            assert_eq!(back.as_raw_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn raw_bits_invalid() {
+        let good = Primes::sieve(1000);
+        let (ub, bits) = (good.upper_bound(), good.as_raw_bits());
+        assert_eq!(ub, 999);
+        let wrong = |expected, found| Some(ParseError::WrongLength { expected: expected, found: found });
+
+        assert_eq!(Primes::from_raw_bits(ub, &bits[1..]).err(), wrong(8, 7));
+        let mut long = bits.to_vec();
+        long.push(0);
+        assert_eq!(Primes::from_raw_bits(ub, &long).err(), wrong(8, 9));
+        assert_eq!(Primes::from_raw_bits(ub + 200, bits).err(), wrong(10, 8));
+        assert_eq!(Primes::from_raw_bits(usize::MAX, bits).err(), wrong(1 << 57, 8));
+
+        let invalid = |why| Some(ParseError::Invalid(why));
+        assert_eq!(Primes::from_raw_bits(ub - 1, bits).err(), invalid("invalid upper bound"));
+        assert_eq!(Primes::from_raw_bits(7, &[0b1110]).err(), invalid("invalid upper bound"));
+
+        let mut one = bits.to_vec();
+        one[0] |= 1;
+        assert_eq!(Primes::from_raw_bits(ub, &one).err(), invalid("1 is marked as prime"));
+        // 1001 = 2 * 500 + 1
+        let mut past = bits.to_vec();
+        past[500 / 64] |= 1 << (500 % 64);
+        assert_eq!(Primes::from_raw_bits(ub, &past).err(),
+                   invalid("numbers past the upper bound are marked as prime"));
+        // but a smaller upper bound isn't.
+        assert!(Primes::from_raw_bits(ub - 2, bits).is_ok());
+    }
+
+    #[test]
+    fn invalid() {
+        let good = Primes::sieve(100).to_bytes();
+        assert!(Primes::from_bytes(&good).is_ok());
+
+        assert_eq!(Primes::from_bytes(&[]).err(),
+                   Some(ParseError::WrongLength { expected: 16, found: 0 }));
+        assert_eq!(Primes::from_bytes(b"nope").err(), Some(ParseError::BadMagic));
+
+        // truncated
+        for len in 0..good.len() {
+            assert!(Primes::from_bytes(&good[..len]).is_err(), "{}", len);
+        }
+        let mut long = good.clone();
+        long.push(0);
+        assert_eq!(Primes::from_bytes(&long).err(),
+                   Some(ParseError::WrongLength { expected: good.len(), found: good.len() + 1 }));
+
+        // corrupt each byte of the header
+        for i in 0..16 {
+            let mut bad = good.clone();
+            bad[i] ^= 0x10;
+            assert!(Primes::from_bytes(&bad).is_err(), "{}", i);
+        }
+        let mut bad = good.clone();
+        bad[0] = b'X';
+        assert_eq!(Primes::from_bytes(&bad).err(), Some(ParseError::BadMagic));
+        let mut bad = good.clone();
+        bad[4] = 2;
+        assert_eq!(Primes::from_bytes(&bad).err(), Some(ParseError::UnsupportedVersion(2)));
+        // an even upper bound
+        let mut bad = good.clone();
+        bad[8] = 98;
+        assert!(Primes::from_bytes(&bad).is_err());
+        // marking 1 as prime
+        let mut bad = good.clone();
+        bad[16] |= 1;
+        assert_eq!(Primes::from_bytes(&bad).err(), Some(ParseError::Invalid("1 is marked as prime")));
+    }
+}
@@ -0,0 +1,114 @@
+use std::vec::Vec;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+
+use Primes;
+
+// The serialised form of a `Primes`: the upper bound, and the bits for
+// the odd numbers packed into bytes.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Primes")]
+struct Repr {
+    upper_bound: u64,
+    bits: Vec<u8>,
+}
+
+impl Serialize for Primes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Repr {
+            upper_bound: self.upper_bound() as u64,
+            bits: self.to_bit_bytes(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Primes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Primes, D::Error> {
+        let repr = Repr::deserialize(deserializer)?;
+        Primes::from_bit_bytes(repr.upper_bound, &repr.bits).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode;
+    use serde_json;
+
+    use Primes;
+
+    fn assert_same(a: &Primes, b: &Primes) {
+        assert_eq!(a.upper_bound(), b.upper_bound());
+        assert!(a.primes().eq(b.primes()));
+    }
+
+    #[test]
+    fn round_trip() {
+        for &limit in [0, 10, 30, 31, 63, 64, 65, 1000, 100_001].iter() {
+            let primes = Primes::sieve(limit);
+            let json = serde_json::to_string(&primes).unwrap();
+            let back: Primes = serde_json::from_str(&json).unwrap();
+            assert_same(&primes, &back);
+            assert_eq!(back.prime_pi(back.upper_bound()), primes.prime_pi(primes.upper_bound()));
+        }
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        for &limit in [0, 10, 31, 1000, 1_000_001].iter() {
+            let primes = Primes::sieve(limit);
+            let bytes = bincode::serialize(&primes).unwrap();
+            // an 8 byte upper bound, and an 8 byte length for the
+            // packed bits.
+            assert_eq!(bytes.len(), 16 + (primes.upper_bound() + 15) / 16);
+
+            let back: Primes = bincode::deserialize(&bytes).unwrap();
+            assert_same(&primes, &back);
+
+            // truncated data is rejected
+            assert!(bincode::deserialize::<Primes>(&bytes[..bytes.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn behaves_identically() {
+        let primes = Primes::sieve(10_000);
+        let json: Primes = serde_json::from_str(&serde_json::to_string(&primes).unwrap()).unwrap();
+        let bin: Primes = bincode::deserialize(&bincode::serialize(&primes).unwrap()).unwrap();
+
+        for back in [json, bin].iter() {
+            for n in 0..back.upper_bound() + 1 {
+                assert_eq!(back.is_prime(n), primes.is_prime(n));
+                assert_eq!(back.prime_pi(n), primes.prime_pi(n));
+            }
+            for n in (1..100_000_000).step_by(9973) {
+                assert_eq!(back.factor(n), primes.factor(n));
+            }
+            assert!(back.primes().rev().eq(primes.primes().rev()));
+            assert!(back.twin_primes().eq(primes.twin_primes()));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let json = serde_json::to_string(&Primes::sieve(100)).unwrap();
+        assert_eq!(json, r#"{"upper_bound":99,"bits":[118,211,45,38,89,72,128]}"#);
+
+        let bad = [
+            // length doesn't match
+            r#"{"upper_bound":99,"bits":[118,211,45,38,89,72]}"#,
+            r#"{"upper_bound":99,"bits":[118,211,45,38,89,72,128,0]}"#,
+            r#"{"upper_bound":999,"bits":[118,211,45,38,89,72,128]}"#,
+            // even, or too small
+            r#"{"upper_bound":98,"bits":[118,211,45,38,89,72,128]}"#,
+            r#"{"upper_bound":3,"bits":[64]}"#,
+            // 1 isn't prime
+            r#"{"upper_bound":99,"bits":[246,211,45,38,89,72,128]}"#,
+            // missing data
+            r#"{"upper_bound":99}"#,
+            ];
+        for &json in bad.iter() {
+            assert!(serde_json::from_str::<Primes>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -1,22 +1,67 @@
-use std::collections::{BitVec, bit_vec};
-use std::{iter, cmp};
+use bit_vec::{self, BitVec, BITS};
+use std::{iter, cmp, fmt, mem};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::vec::Vec;
 
-use Factors;
+use {Factors, FactorError};
+use float;
+use fast_sieve::StreamingSieve;
+use pollard_rho;
+
+// the number of blocks between each entry of the prime count index.
+const INDEX_STRIDE: usize = 8;
 
 /// Stores information about primes up to some limit.
 ///
-/// This uses at least `limit / 16 + O(1)` bytes of storage.
+/// This uses at least `limit / 16 + O(1)` bytes of storage, plus a
+/// prime count index of about an eighth of that.
+#[derive(Clone)]
 pub struct Primes {
     // This only stores odd numbers, since even numbers are mostly
     // non-prime.
-    v: BitVec
+    v: BitVec,
+    // `counts[i]` is the number of primes (including 2) stored in the
+    // first `i * INDEX_STRIDE` blocks of `v`.
+    counts: Vec<usize>,
 }
 
 /// Iterator over the primes stored in a sieve.
+///
+/// This knows exactly how many primes it has left, via `len()`.
 #[derive(Clone)]
 pub struct PrimeIterator<'a> {
     two: bool,
-    iter: iter::Enumerate<bit_vec::Iter<'a>>,
+    v: &'a BitVec,
+    // the indices into `v` that haven't been visited yet are
+    // `lo..hi`.
+    lo: usize,
+    hi: usize,
+    // the number of primes left to yield.
+    remaining: usize,
+}
+
+/// Iterator over the primes stored in a sieve, paired with the gap
+/// to the next one.
+#[derive(Clone)]
+pub struct PrimeGaps<'a> {
+    // the primes strictly between `lo` and `hi`.
+    iter: PrimeIterator<'a>,
+    // the smaller prime of the next pair from the front, and the
+    // larger prime of the next pair from the back.
+    lo: Option<usize>,
+    hi: Option<usize>,
+}
+
+/// Iterator over the pairs of twin primes (*p*, *p* + 2) stored in a
+/// sieve.
+#[derive(Clone)]
+pub struct TwinPrimeIterator<'a> {
+    blocks: iter::Peekable<bit_vec::Blocks<'a>>,
+    // the index of the first bit of the block `pending` came from.
+    base: usize,
+    // bit `i` is set if `base + i` and `base + i + 1` are both prime.
+    pending: u64,
 }
 
 impl Primes {
@@ -25,15 +70,16 @@ impl Primes {
     /// This stores all primes less than `limit` (and possibly some
     /// more), allowing for very efficient iteration and primality
     /// testing below this, and guarantees that all numbers up to
-    /// `limit^2` can be factorised.
+    /// `upper_bound()^2`, which is at least `(limit - 1)^2`, can be
+    /// factorised.
     pub fn sieve(limit: usize) -> Primes {
         // having this out-of-line like this is faster (130 us/iter
         // vs. 111 us/iter on sieve_large), and using a manual while
         // rather than a `range_step` is a similar speedup.
         #[inline(never)]
-        fn filter(is_prime: &mut BitVec, limit: usize, check: usize, p: usize) {
+        fn filter(is_prime: &mut BitVec, check: usize, p: usize) {
             let mut zero = 2 * check * (check + 1);
-            while zero < limit / 2 {
+            while zero < is_prime.len() {
                 is_prime.set(zero, false);
                 zero += p;
             }
@@ -48,32 +94,522 @@ impl Primes {
 
         // multiples of 3 aren't prime (3 is handled separately, so
         // the ticking works properly)
-        filter(&mut is_prime, limit, 1, 3);
+        filter(&mut is_prime, 1, 3);
 
-        let bound = (limit as f64).sqrt() as usize + 1;
+        let bound = float::sqrt(limit as f64) as usize + 1;
         // skip 2.
         let mut check = 2;
         let mut tick = if check % 3 == 1 {2} else {1};
 
         while check <= bound {
             if is_prime[check] {
-                filter(&mut is_prime, limit, check, 2 * check + 1)
+                filter(&mut is_prime, check, 2 * check + 1)
             }
 
             check += tick;
             tick = 3 - tick;
         }
 
-        Primes { v: is_prime }
+        Primes::from_bits(is_prime)
+    }
+
+    /// Construct a `Primes` via a sieve up to at least `limit`, using
+    /// a mod-30 wheel.
+    ///
+    /// This gives exactly the same result as `Primes::sieve(limit)`,
+    /// but, after removing the multiples of 3 and 5, only crosses off
+    /// the multiples `p * q` of each prime where `q` is coprime to
+    /// 30, which is about half as many as `sieve` does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve_wheel(1000);
+    /// assert_eq!(sieve.nth_prime(100), Some(547));
+    /// ```
+    pub fn sieve_wheel(limit: usize) -> Primes {
+        // the numbers coprime to 30, and the gaps to the next one.
+        const WHEEL: [usize; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+        const GAPS: [usize; 8] = [6, 4, 2, 4, 2, 4, 6, 2];
+
+        #[inline(never)]
+        fn filter(is_prime: &mut BitVec, p: usize) {
+            // the steps between the indices of the multiples of p by
+            // numbers coprime to 30, starting at p^2.
+            let mut steps = [0; 8];
+            let start = WHEEL.iter().position(|&w| w == p % 30).unwrap();
+            for i in 0..8 {
+                steps[i] = p * GAPS[(start + i) % 8] / 2;
+            }
+
+            let len = is_prime.len();
+            let mut zero = p * p / 2;
+            'outer: loop {
+                for &step in steps.iter() {
+                    if zero >= len { break 'outer }
+                    is_prime.set(zero, false);
+                    zero += step;
+                }
+            }
+        }
+
+        // match `sieve`.
+        let limit = cmp::max(10, limit);
+
+        let mut is_prime = BitVec::from_elem((limit + 1) / 2, true);
+        // 1 isn't prime
+        is_prime.set(0, false);
+        // remove the odd multiples of 3 and 5, other than themselves.
+        for &p in [3, 5].iter() {
+            let mut zero = p * p / 2;
+            while zero < is_prime.len() {
+                is_prime.set(zero, false);
+                zero += p;
+            }
+        }
+
+        let bound = float::sqrt(limit as f64) as usize + 1;
+        let mut check = 7;
+        let mut i = 1;
+        while check <= bound {
+            if is_prime[check / 2] {
+                filter(&mut is_prime, check)
+            }
+
+            check += GAPS[i];
+            i = (i + 1) % 8;
+        }
+
+        Primes::from_bits(is_prime)
+    }
+
+    /// Construct a `Primes` via the Sieve of Atkin, up to at least
+    /// `limit`.
+    ///
+    /// This gives exactly the same result as `Primes::sieve(limit)`.
+    /// Instead of crossing off multiples of primes, it counts the
+    /// solutions of a few quadratic forms, `4x^2 + y^2`, `3x^2 + y^2`
+    /// and `3x^2 - y^2`, for each number, and then removes the ones
+    /// divisible by a square. This does asymptotically less work, but
+    /// this straightforward version is only about as fast as `sieve`
+    /// in practice; it's mainly useful for comparison.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve_atkin(1000);
+    /// assert_eq!(sieve.nth_prime(100), Some(547));
+    /// ```
+    pub fn sieve_atkin(limit: usize) -> Primes {
+        fn flip(is_prime: &mut BitVec, n: usize) {
+            let i = n / 2;
+            let x = is_prime[i];
+            is_prime.set(i, !x);
+        }
+
+        // match `sieve`.
+        let limit = cmp::max(10, limit);
+        let len = (limit + 1) / 2;
+        // the largest number stored.
+        let top = 2 * len - 1;
+
+        let mut is_prime = BitVec::from_elem(len, false);
+
+        // n = 4x^2 + y^2 with n = 1, 5 (mod 12), which needs y odd.
+        let mut x = 1;
+        while 4 * x * x < top {
+            let mut y = 1;
+            while 4 * x * x + y * y <= top {
+                let n = 4 * x * x + y * y;
+                if n % 12 == 1 || n % 12 == 5 {
+                    flip(&mut is_prime, n)
+                }
+                y += 2;
+            }
+            x += 1;
+        }
+        // n = 3x^2 + y^2 with n = 7 (mod 12), which needs x odd and
+        // y even.
+        let mut x = 1;
+        while 3 * x * x < top {
+            let mut y = 2;
+            while 3 * x * x + y * y <= top {
+                let n = 3 * x * x + y * y;
+                if n % 12 == 7 {
+                    flip(&mut is_prime, n)
+                }
+                y += 2;
+            }
+            x += 2;
+        }
+        // n = 3x^2 - y^2 with x > y and n = 11 (mod 12), which needs
+        // x and y to have different parities. The smallest such n
+        // for each x is when y = x - 1.
+        let mut x = 2;
+        while 2 * x * x + 2 * x - 1 <= top {
+            let mut y = x - 1;
+            loop {
+                let n = 3 * x * x - y * y;
+                if n > top { break }
+                if n % 12 == 11 {
+                    flip(&mut is_prime, n)
+                }
+                if y <= 2 { break }
+                y -= 2;
+            }
+            x += 1;
+        }
+
+        // none of the forms find 3.
+        is_prime.set(1, true);
+
+        // the forms find all primes, and the composites with a
+        // square factor; remove the latter using the squares of
+        // primes (any other square is a multiple of one of these).
+        let mut r = 5;
+        while r * r <= top {
+            if is_prime[r / 2] {
+                let square = r * r;
+                let mut n = square;
+                while n <= top {
+                    is_prime.set(n / 2, false);
+                    n += 2 * square;
+                }
+            }
+            r += 2;
+        }
+
+        Primes::from_bits(is_prime)
+    }
+
+    /// Construct a `Primes` via a sieve up to at least `limit`,
+    /// filtering `segment_size` numbers at a time.
+    ///
+    /// This gives exactly the same result as `Primes::sieve(limit)`,
+    /// but the sieving itself only touches `O(sqrt(limit) +
+    /// segment_size)` memory at once, which is more cache-friendly.
+    /// The resulting `Primes` still needs `limit / 16 + O(1)` bytes
+    /// to support random access; use `SegmentedSieve` to enumerate
+    /// primes without storing them all.
+    ///
+    /// # Panics
+    ///
+    /// If `segment_size` is zero.
+    pub fn segmented_sieve(limit: usize, segment_size: usize) -> Primes {
+        // match `sieve`.
+        let limit = cmp::max(10, limit);
+        let len = (limit + 1) / 2;
+
+        let mut stream = StreamingSieve::with_segment_size(limit, segment_size);
+        let mut is_prime = BitVec::with_capacity(len);
+        while let Some((_, segment)) = stream.next() {
+            is_prime.append(segment);
+        }
+        // the last segment may go past the limit, and leave the
+        // storage over-allocated.
+        is_prime.truncate(len);
+        is_prime.shrink_to_fit();
+
+        Primes::from_bits(is_prime)
+    }
+
+    /// Sieve the odd numbers with indices `lo..hi` (that is, the
+    /// numbers `2 * lo + 1` up to `2 * hi - 1`) using the primes
+    /// stored in `base`, which must include every prime up to
+    /// `sqrt(2 * hi - 1)`.
+    ///
+    /// Bit `i` of the result corresponds to index `lo + i`, so the
+    /// segments can be computed independently and concatenated.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn sieve_segment(base: &Primes, lo: usize, hi: usize) -> BitVec {
+        let mut segment = BitVec::from_elem(hi - lo, true);
+        if lo == 0 && hi > 0 {
+            // 1 isn't prime
+            segment.set(0, false);
+        }
+
+        let top = 2 * hi - 1;
+        for p in base.primes().skip(1) {
+            if p > top / p { break }
+
+            // the first odd multiple of p that is both at least p^2
+            // and in the segment.
+            let mut q = cmp::max(p, (2 * lo + 1 + p - 1) / p);
+            if q % 2 == 0 { q += 1 }
+
+            let mut zero = q * p / 2 - lo;
+            while zero < hi - lo {
+                segment.set(zero, false);
+                zero += p;
+            }
+        }
+        segment
+    }
+
+    /// Construct a `Primes` storing at least the first `n_primes`
+    /// primes, by sieving once.
+    ///
+    /// The limit is the upper bound on the `n_primes`th prime given
+    /// by `estimate_nth_prime`, which is rigorous, and so this never
+    /// sieves too little (but may sieve up to a few percent more than
+    /// necessary).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::with_at_least(10001);
+    /// assert_eq!(sieve.nth_prime(10000), Some(104743));
+    /// ```
+    pub fn with_at_least(n_primes: usize) -> Primes {
+        let (_, hi) = ::estimate_nth_prime(n_primes as u64);
+        Primes::sieve(hi as usize)
+    }
+
+    /// Construct a `Primes` that can factorise every number up to
+    /// `max`, by sieving only up to about `sqrt(max)`.
+    ///
+    /// This is much smaller than `Primes::sieve(max)` (which would
+    /// be needed to test all those numbers for primality, via
+    /// `is_prime`), and guarantees that `self.upper_bound()^2 >= max`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::for_factoring(1_000_000_000_000);
+    /// assert!(sieve.upper_bound() < 2_000_000);
+    /// assert_eq!(sieve.factor(999_999_999_989).unwrap(), [(999_999_999_989, 1)]);
+    /// ```
+    pub fn for_factoring(max: usize) -> Primes {
+        // the floating point square root may be slightly low, and
+        // `sieve` only promises `limit - 1`.
+        Primes::sieve(float::sqrt(max as f64) as usize + 2)
+    }
+
+    /// The bits for the odd numbers, packed into bytes as by
+    /// `BitVec::to_bytes`.
+    pub(crate) fn to_bit_bytes(&self) -> Vec<u8> {
+        self.v.to_bytes()
+    }
+
+    /// Reconstruct a `Primes` with the given upper bound from the
+    /// output of `to_bit_bytes`, checking that they're consistent.
+    pub(crate) fn from_bit_bytes(upper_bound: u64, bytes: &[u8]) -> Result<Primes, &'static str> {
+        let len = stored_bits(upper_bound)?;
+        if bytes.len() != (len + 7) / 8 {
+            return Err("length of the bit data doesn't match the upper bound")
+        }
+        if bytes[0] & 0x80 != 0 {
+            return Err("1 is marked as prime")
+        }
+
+        let mut v = BitVec::from_bytes(bytes);
+        v.truncate(len);
+        Ok(Primes::from_bits(v))
+    }
+
+    /// The blocks of bits for the odd numbers, as stored.
+    pub(crate) fn bit_blocks(&self) -> &[u64] {
+        self.v.as_blocks()
+    }
+
+    /// Reconstruct a `Primes` with the given upper bound from the
+    /// output of `bit_blocks`, checking that they're consistent.
+    pub(crate) fn from_bit_blocks(upper_bound: u64, blocks: &[u64]) -> Result<Primes, &'static str> {
+        let len = stored_bits(upper_bound)?;
+        if blocks.len() != (len + BITS - 1) / BITS {
+            return Err("length of the bit data doesn't match the upper bound")
+        }
+        if blocks[0] & 1 != 0 {
+            return Err("1 is marked as prime")
+        }
+        if len % BITS != 0 && blocks[blocks.len() - 1] >> (len % BITS) != 0 {
+            return Err("numbers past the upper bound are marked as prime")
+        }
+
+        Ok(Primes::from_bits(BitVec::from_blocks(blocks.to_vec(), len)))
+    }
+
+    pub(crate) fn from_bits(v: BitVec) -> Primes {
+        let mut primes = Primes { v: v, counts: vec![] };
+        primes.build_index();
+        primes
+    }
+
+    fn build_index(&mut self) {
+        self.counts = Vec::with_capacity(self.v.len() / (BITS * INDEX_STRIDE) + 2);
+        // 2 isn't stored in the bit vector.
+        self.counts.push(1);
+        self.update_index(0);
+    }
+
+    // Recompute the prime count index after the blocks from `first`
+    // onwards have changed, reusing the entries before them.
+    fn update_index(&mut self, first: usize) {
+        let start = first / INDEX_STRIDE;
+        self.counts.truncate(start + 1);
+        let mut count = self.counts[start];
+        for (i, block) in self.v.blocks().enumerate().skip(start * INDEX_STRIDE) {
+            if i % INDEX_STRIDE == 0 && i > start * INDEX_STRIDE {
+                self.counts.push(count)
+            }
+            count += block.count_ones() as usize;
+        }
+        self.counts.push(count);
+    }
+
+    /// Extend this sieve in place so that it stores all the primes
+    /// less than `new_limit`, as if it had been constructed with
+    /// `Primes::sieve(new_limit)`.
+    ///
+    /// Only the new part of the sieve is filtered, using the primes
+    /// already stored (and any new ones up to `sqrt(new_limit)`), and
+    /// only the new part of the prime count index is computed, so a
+    /// sieve can be grown in many small steps without redoing the
+    /// work for the old part each time. This does nothing if
+    /// `new_limit` is not larger than the upper bound.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut sieve = slow_primes::Primes::sieve(1000);
+    /// assert_eq!(sieve.count_primes(), 168);
+    ///
+    /// sieve.grow_to(10_000);
+    /// assert_eq!(sieve.count_primes(), 1229);
+    /// assert!(sieve.is_prime(9973));
+    /// ```
+    pub fn grow_to(&mut self, new_limit: usize) {
+        if new_limit <= self.upper_bound() {
+            return
+        }
+
+        let old_len = self.v.len();
+        let new_len = (cmp::max(10, new_limit) + 1) / 2;
+        if new_len <= old_len {
+            return
+        }
+        self.v.grow(new_len - old_len, true);
+
+        // the smallest number in the new tail.
+        let first_new = 2 * old_len + 1;
+        let mut check = 1;
+        loop {
+            let p = 2 * check + 1;
+            if p * p >= 2 * new_len {
+                break
+            }
+            // anything in the new part that is still set by the time
+            // we reach it is prime, since all its smaller potential
+            // factors have already been filtered.
+            if self.v[check] {
+                // the first odd multiple of p that is both at least
+                // p^2 and in the new part.
+                let mut q = cmp::max(p, (first_new + p - 1) / p);
+                if q % 2 == 0 { q += 1 }
+
+                let mut zero = q * p / 2;
+                while zero < new_len {
+                    self.v.set(zero, false);
+                    zero += p;
+                }
+            }
+            check += 1;
+        }
+        self.update_index(old_len / BITS);
     }
 
     /// The largest number stored.
+    ///
+    /// Only odd numbers are stored, so this is always odd, and may be
+    /// one less than the limit the sieve was constructed with. This
+    /// is the bound that matters for `is_prime` (which panics on odd
+    /// numbers above it) and `factor` (which can factorise every
+    /// number up to its square).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    /// assert_eq!(sieve.upper_bound(), 99);
+    /// assert_eq!(sieve.sieve_limit(), 100);
+    /// ```
     pub fn upper_bound(&self) -> usize {
         (self.v.len() - 1) * 2 + 1
     }
 
+    /// The number of bytes of heap memory used by this sieve: the bit
+    /// vector itself and the prime count index, counting any spare
+    /// capacity they have (for example, after `grow_to`).
+    ///
+    /// For a sieve made by `Primes::sieve(limit)`, this is exactly
+    /// `estimated_sieve_memory(limit)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::{Primes, estimated_sieve_memory};
+    ///
+    /// let sieve = Primes::sieve(1_000_000);
+    /// assert_eq!(sieve.memory_usage(), estimated_sieve_memory(1_000_000));
+    /// // about a million / 16 bytes for the bits, and an eighth of
+    /// // that for the index.
+    /// assert!(62_500 <= sieve.memory_usage() && sieve.memory_usage() < 72_000);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        self.v.heap_size() + self.counts.capacity() * mem::size_of::<usize>()
+    }
+
+    /// The limit covered by this sieve, `self.upper_bound() + 1`.
+    ///
+    /// This is at least the limit passed to the constructor (other
+    /// than that limits below 10 are rounded up), and every prime less
+    /// than or equal to it is stored, since the extra number beyond
+    /// the upper bound is even.
+    pub fn sieve_limit(&self) -> usize {
+        self.upper_bound() + 1
+    }
+
+    /// Whether `n` is in the range stored by this sieve, that is,
+    /// whether `n <= self.upper_bound()`, so that `is_prime(n)` will
+    /// not panic.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    /// assert!(sieve.contains(97));
+    /// assert!(!sieve.contains(101));
+    /// ```
+    pub fn contains(&self, n: usize) -> bool {
+        n <= self.upper_bound()
+    }
+
+    /// The number of primes stored in this sieve, that is, π of the
+    /// upper bound.
+    ///
+    /// This takes constant time, since it is recorded when sieving
+    /// (as the running popcount of the bit vector, in the index used
+    /// by `prime_pi` and `nth_prime`). `self.primes().count()` is
+    /// also constant time, since the iterator knows its length.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1_000_000);
+    /// assert_eq!(sieve.count_primes(), 78498);
+    /// assert_eq!(sieve.primes().len(), 78498);
+    /// ```
+    pub fn count_primes(&self) -> usize {
+        self.counts[self.counts.len() - 1]
+    }
+
     /// Check if `n` is prime, possibly failing if `n` is larger than
     /// the upper bound of this Primes instance.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is odd and larger than `self.upper_bound()`. Use
+    /// `checked_is_prime` to handle arbitrary inputs.
     pub fn is_prime(&self, n: usize) -> bool {
         if n % 2 == 0 {
             // 2 is the evenest prime.
@@ -84,167 +620,1705 @@ impl Primes {
         }
     }
 
+    /// Check if `n` is prime, without checking that `n` is in range.
+    ///
+    /// This gives exactly the same answers as `is_prime`, but skips
+    /// the bounds checks, for hot loops where `n` is already known to
+    /// be small enough.
+    ///
+    /// # Safety
+    ///
+    /// If `n` is odd, it must be no larger than `self.upper_bound()`
+    /// (even numbers are always fine). Otherwise, this reads out of
+    /// bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
+    ///
+    /// let count = (0..1000).filter(|&n| unsafe { sieve.is_prime_unchecked(n) }).count();
+    /// assert_eq!(count, 168);
+    /// ```
+    #[inline]
+    pub unsafe fn is_prime_unchecked(&self, n: usize) -> bool {
+        if n % 2 == 0 {
+            n == 2
+        } else {
+            self.v.get_unchecked(n / 2)
+        }
+    }
+
+    /// Check if `n` is prime, returning `None` if `n` is larger than
+    /// the upper bound of this Primes instance.
+    ///
+    /// Even numbers never need the sieve, so this always returns
+    /// `Some` for them.
+    pub fn checked_is_prime(&self, n: usize) -> Option<bool> {
+        if n % 2 == 0 {
+            Some(n == 2)
+        } else if n <= self.upper_bound() {
+            Some(self.v[n / 2])
+        } else {
+            None
+        }
+    }
+
     /// Iterator over the primes stored in this map.
     pub fn primes<'a>(&'a self) -> PrimeIterator<'a> {
         PrimeIterator {
             two: true,
-            iter: self.v.iter().enumerate()
+            v: &self.v,
+            lo: 0,
+            hi: self.v.len(),
+            remaining: self.count_primes(),
         }
     }
 
-    /// Factorise `n` into (prime, exponent) pairs.
+    /// Iterator over the primes stored in this map, the same as
+    /// `self.primes()`, or `&self` in a `for` loop.
     ///
-    /// Returns `Err((leftover, partial factorisation))` if `n` cannot
-    /// be fully factored, or if `n` is zero (`leftover == 0`). A
-    /// number can not be completely factored if and only if the prime
-    /// factors of `n` are too large for this sieve, that is, if there
-    /// is
+    /// # Examples
     ///
-    /// - a prime factor larger than `U^2`, or
-    /// - more than one prime factor between `U` and `U^2`
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
     ///
-    /// where `U` is the upper bound of the primes stored in this
-    /// sieve.
+    /// assert!(sieve.iter().eq(sieve.primes()));
     ///
-    /// Notably, any number between `U` and `U^2` can always be fully
-    /// factored, since these numbers are guaranteed to only have zero
-    /// or one prime factors larger than `U`.
-    pub fn factor(&self, mut n: usize) -> Result<Factors, (usize, Factors)> {
-        if n == 0 { return Err((0, vec![])) }
+    /// let mut sum = 0;
+    /// for p in &sieve {
+    ///     sum += p;
+    /// }
+    /// assert_eq!(sum, 76127);
+    /// ```
+    pub fn iter<'a>(&'a self) -> PrimeIterator<'a> {
+        self.primes()
+    }
 
-        let mut ret = Vec::new();
+    /// Iterator over the primes `p` stored in this map with `low <= p
+    /// < high`.
+    ///
+    /// This starts directly at `low`, rather than skipping over all
+    /// the smaller primes. If `high` is larger than the upper bound,
+    /// the iterator stops at the last prime stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
+    ///
+    /// assert_eq!(sieve.primes_in_range(100, 130).collect::<Vec<_>>(),
+    ///            [101, 103, 107, 109, 113, 127]);
+    /// assert_eq!(sieve.primes_in_range(990, 2000).collect::<Vec<_>>(), [991, 997]);
+    /// ```
+    pub fn primes_in_range<'a>(&'a self, low: usize, high: usize) -> PrimeIterator<'a> {
+        // the index of the first odd number >= x is x / 2.
+        let hi = cmp::min(high / 2, self.v.len());
+        let lo = cmp::min(low / 2, hi);
+        let two = low <= 2 && 2 < high;
+        PrimeIterator {
+            two: two,
+            v: &self.v,
+            lo: lo,
+            hi: hi,
+            remaining: two as usize + self.rank(hi) - self.rank(lo),
+        }
+    }
 
-        for p in self.primes() {
-            if n == 1 { break }
+    /// Iterator over the primes stored in this map that lie in
+    /// `range`, equivalent to `self.primes_in_range(range.start,
+    /// range.end)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
+    ///
+    /// assert_eq!(sieve.primes_in(0..10).collect::<Vec<_>>(), [2, 3, 5, 7]);
+    /// assert_eq!(sieve.primes_in(900..920).rev().collect::<Vec<_>>(), [919, 911, 907]);
+    /// ```
+    pub fn primes_in<'a>(&'a self, range: Range<usize>) -> PrimeIterator<'a> {
+        self.primes_in_range(range.start, range.end)
+    }
 
-            let mut count = 0;
-            while n % p == 0 {
-                n /= p;
-                count += 1;
-            }
-            if count > 0 {
-                ret.push((p,count));
-            }
+    /// Iterator over `(p, gap)` pairs, for each prime `p` stored in
+    /// this map and the distance `gap` from it to the next prime.
+    ///
+    /// The last prime stored is omitted, since the next prime isn't
+    /// known.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(30);
+    ///
+    /// assert_eq!(sieve.gaps().take(4).collect::<Vec<_>>(),
+    ///            [(2, 1), (3, 2), (5, 2), (7, 4)]);
+    /// // 29 is the last prime stored.
+    /// assert_eq!(sieve.gaps().last(), Some((23, 6)));
+    /// ```
+    pub fn gaps<'a>(&'a self) -> PrimeGaps<'a> {
+        let mut iter = self.primes();
+        let lo = iter.next();
+        let hi = iter.next_back();
+        PrimeGaps { iter: iter, lo: lo, hi: hi }
+    }
+
+    /// Iterator over the gaps between consecutive primes stored in
+    /// this map, that is, `3 - 2`, `5 - 3`, `7 - 5`, ....
+    ///
+    /// This is `self.gaps()` without the primes themselves, and so
+    /// also reads the bits directly, never storing all the primes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(30);
+    ///
+    /// assert_eq!(sieve.prime_gaps().collect::<Vec<_>>(),
+    ///            [1, 2, 2, 4, 2, 4, 2, 4, 6]);
+    /// ```
+    pub fn prime_gaps<'a>(&'a self) -> impl DoubleEndedIterator<Item = usize> + 'a {
+        self.gaps().map(|(_, gap)| gap)
+    }
+
+    /// Find the consecutive primes `(p, q)` stored in this map that
+    /// are furthest apart, returning the first such pair if there are
+    /// several, or `None` if fewer than two primes are stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
+    ///
+    /// // the gap of 20 after 887 is the largest below 1000.
+    /// assert_eq!(sieve.max_prime_gap(), Some((887, 907)));
+    /// ```
+    pub fn max_prime_gap(&self) -> Option<(usize, usize)> {
+        self.gaps()
+            .fold(None, |best: Option<(usize, usize)>, (p, gap)| match best {
+                Some((_, g)) if g >= gap => best,
+                _ => Some((p, gap)),
+            })
+            .map(|(p, gap)| (p, p + gap))
+    }
+
+    /// Iterator over the pairs of twin primes (*p*, *p* + 2) where
+    /// both are stored in this map, in increasing order.
+    ///
+    /// This finds adjacent set bits a whole block at a time, rather
+    /// than testing each candidate individually.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(50);
+    ///
+    /// assert_eq!(sieve.twin_primes().collect::<Vec<_>>(),
+    ///            [(3, 5), (5, 7), (11, 13), (17, 19), (29, 31), (41, 43)]);
+    /// ```
+    pub fn twin_primes<'a>(&'a self) -> TwinPrimeIterator<'a> {
+        TwinPrimeIterator {
+            blocks: self.v.blocks().peekable(),
+            base: 0,
+            pending: 0,
         }
-        if n != 1 {
-            let b = self.upper_bound();
-            if b * b >= n {
-                // n is not divisible by anything from 1...sqrt(n), so
-                // must be prime itself! (That is, even though we
-                // don't know this prime specifically, we can infer
-                // that it must be prime.)
-                ret.push((n, 1));
-            } else {
-                // large factors :(
-                return Err((n, ret))
+    }
+
+    /// Iterator over the Sophie Germain primes stored in this map,
+    /// that is, the primes `p` where `2p + 1` is also prime, in
+    /// increasing order.
+    ///
+    /// Only primes with `2p + 1 <= self.upper_bound()` can be
+    /// checked, and so the ones above `self.upper_bound() / 2` are
+    /// skipped. Conveniently, `2p + 1` is stored at index `p` of the
+    /// bit vector, so each candidate is a single lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.sophie_germain_primes().collect::<Vec<_>>(),
+    ///            [2, 3, 5, 11, 23, 29, 41]);
+    /// ```
+    pub fn sophie_germain_primes<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.primes_in_range(0, self.v.len()).filter(move |&p| self.v[p])
+    }
+
+    /// Iterator over the safe primes stored in this map, that is, the
+    /// primes `q` where `(q - 1) / 2` is also prime, in increasing
+    /// order.
+    ///
+    /// These are exactly `2p + 1` for the Sophie Germain primes `p`,
+    /// and since `(q - 1) / 2` is always smaller than `q`, every safe
+    /// prime up to `self.upper_bound()` is found.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(200);
+    ///
+    /// assert_eq!(sieve.safe_primes().collect::<Vec<_>>(),
+    ///            [5, 7, 11, 23, 47, 59, 83, 107, 167, 179]);
+    /// ```
+    pub fn safe_primes<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.sophie_germain_primes().map(|p| 2 * p + 1)
+    }
+
+    /// Iterator over the prime powers `p^k` (with `k >= 1`) up to the
+    /// upper bound of this sieve, as `(p^k, p, k)` triples, in
+    /// increasing order of `p^k`.
+    ///
+    /// The powers with `k >= 2` are all at most `self.upper_bound()`,
+    /// so their bases are at most its square root; these are listed
+    /// and sorted up front (taking `O(sqrt(upper_bound))` memory), and
+    /// then merged with the primes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(20);
+    ///
+    /// let powers = sieve.prime_powers().map(|(q, _, _)| q).collect::<Vec<_>>();
+    /// assert_eq!(powers, [2, 3, 4, 5, 7, 8, 9, 11, 13, 16, 17, 19]);
+    /// assert_eq!(sieve.prime_powers().nth(9), Some((16, 2, 4)));
+    /// ```
+    pub fn prime_powers<'a>(&'a self) -> impl Iterator<Item = (usize, usize, u32)> + 'a {
+        let ub = self.upper_bound();
+        let mut powers = vec![];
+        for p in self.primes() {
+            let mut q = match p.checked_mul(p) {
+                Some(q) if q <= ub => q,
+                _ => break
+            };
+            let mut k = 2;
+            loop {
+                powers.push((q, p, k));
+                q = match q.checked_mul(p) {
+                    Some(q) if q <= ub => q,
+                    _ => break
+                };
+                k += 1;
             }
         }
-        Ok(ret)
+        powers.sort();
+
+        let mut primes = self.primes().peekable();
+        let mut powers = powers.into_iter().peekable();
+        iter::from_fn(move || {
+            match (primes.peek(), powers.peek()) {
+                (Some(&p), Some(&(q, _, _))) if q < p => powers.next(),
+                (Some(_), _) => primes.next().map(|p| (p, p, 1)),
+                (None, _) => powers.next(),
+            }
+        })
     }
-}
 
-impl<'a> Iterator for PrimeIterator<'a> {
+    /// Count the primes less than or equal to `n`, that is, compute
+    /// π(*n*) exactly, or return `None` if `n` is larger than the
+    /// upper bound of this Primes instance.
+    ///
+    /// This takes constant time, using an index of prime counts
+    /// built when the sieve is constructed (one `usize` for every
+    /// 512 odd numbers, about an eighth of the size of the sieve
+    /// itself).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(1000);
+    ///
+    /// assert_eq!(sieve.prime_pi(10), Some(4));
+    /// assert_eq!(sieve.prime_pi(100), Some(25));
+    /// assert_eq!(sieve.prime_pi(10000), None);
+    /// ```
+    pub fn prime_pi(&self, n: usize) -> Option<usize> {
+        if n > self.upper_bound() { return None }
+        if n < 2 { return Some(0) }
+
+        // 2, and the odd primes less than or equal to `n`.
+        Some(1 + self.rank((n + 1) / 2))
+    }
+
+    // The number of set bits of `v` with index less than `end`, that
+    // is, the number of odd primes less than `2 * end`.
+    fn rank(&self, end: usize) -> usize {
+        let (full, partial) = (end / BITS, end % BITS);
+
+        let chunk = full / INDEX_STRIDE;
+        // the index counts 2 too.
+        let mut count = self.counts[chunk] - 1;
+        let mut blocks = self.v.blocks().skip(chunk * INDEX_STRIDE);
+        for block in blocks.by_ref().take(full % INDEX_STRIDE) {
+            count += block.count_ones() as usize;
+        }
+        if partial > 0 {
+            let block = blocks.next().unwrap();
+            count += (block & ((1 << partial) - 1)).count_ones() as usize;
+        }
+        count
+    }
+
+    /// Return the smallest prime strictly larger than `n`, or `None`
+    /// if it is larger than the upper bound of this Primes instance.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.next_prime(2), Some(3));
+    /// assert_eq!(sieve.next_prime(4), Some(5));
+    /// assert_eq!(sieve.next_prime(89), Some(97));
+    /// assert_eq!(sieve.next_prime(97), None);
+    /// ```
+    pub fn next_prime(&self, n: usize) -> Option<usize> {
+        if n < 2 { return Some(2) }
+        // (this also stops n + 1 overflowing.)
+        if n >= self.upper_bound() { return None }
+
+        // the index of the first odd number after n.
+        let i = (n + 1) / 2;
+        let blocks = self.v.as_blocks();
+        let mut b = i / BITS;
+        // ignore the numbers up to n in the first block.
+        let mut block = blocks[b] & (!0 << (i % BITS));
+        loop {
+            if block != 0 {
+                return Some(2 * (b * BITS + block.trailing_zeros() as usize) + 1)
+            }
+            b += 1;
+            // the bits past the end are zero.
+            block = *blocks.get(b)?;
+        }
+    }
+
+    /// Return the largest prime strictly smaller than `n`, or `None`
+    /// if there is no such prime (`n <= 2`) or if `n` is larger than
+    /// `self.upper_bound() + 1`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.prev_prime(2), None);
+    /// assert_eq!(sieve.prev_prime(3), Some(2));
+    /// assert_eq!(sieve.prev_prime(97), Some(89));
+    /// assert_eq!(sieve.prev_prime(100), Some(97));
+    /// ```
+    pub fn prev_prime(&self, n: usize) -> Option<usize> {
+        if n <= 2 || n > self.upper_bound() + 1 { return None }
+
+        // the index of the last odd number before n; index 0 is 1,
+        // which isn't prime, so it's never set.
+        let mut i = (n - 1) / 2;
+        if n % 2 == 1 { i -= 1 }
+        let blocks = self.v.as_blocks();
+        let mut b = i / BITS;
+        // ignore the numbers from n onwards in the first block.
+        let mut block = blocks[b] & (!0 >> (BITS - 1 - i % BITS));
+        loop {
+            if block != 0 {
+                return Some(2 * (b * BITS + BITS - 1 - block.leading_zeros() as usize) + 1)
+            }
+            if b == 0 { return Some(2) }
+            b -= 1;
+            block = blocks[b];
+        }
+    }
+
+    /// Return the `n`th prime stored in this sieve, zero-indexed (so
+    /// `nth_prime(0) == Some(2)`), or `None` if there are not that
+    /// many primes stored.
+    ///
+    /// This uses the index of prime counts that `prime_pi` uses to
+    /// jump (by binary search) most of the way there, and then skips
+    /// over whole blocks of the underlying bit vector by counting
+    /// their set bits, so it takes `O(log n)` time, rather than the
+    /// `O(n)` of `self.primes().nth(n)`. Calling this for every `n`
+    /// in turn is fine, although `self.primes()` is faster for that.
+    ///
+    /// `Primes` doesn't implement `Index` as a shorthand for this,
+    /// since `Index` has to return a reference, and the primes aren't
+    /// stored anywhere for it to point to, only the bits marking
+    /// them. Use `get` for a version that panics when `n` is out of
+    /// range instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.nth_prime(0), Some(2));
+    /// assert_eq!(sieve.nth_prime(24), Some(97));
+    /// // there are only 25 primes below 100.
+    /// assert_eq!(sieve.nth_prime(25), None);
+    /// ```
+    pub fn nth_prime(&self, n: usize) -> Option<usize> {
+        // 2 isn't stored in the bit vector.
+        if n == 0 { return Some(2) }
+
+        if n >= self.count_primes() { return None }
+
+        // the last chunk of the index starting at or before the `n`th
+        // prime, which isn't the final entry (the total).
+        let chunk = self.counts.partition_point(|&c| c <= n) - 1;
+        let mut n = n - self.counts[chunk];
+        let start = chunk * INDEX_STRIDE;
+        for (i, mut block) in self.v.blocks().skip(start).enumerate() {
+            let count = block.count_ones() as usize;
+            if n < count {
+                // clear the lowest set bits until the one we want is
+                // the lowest.
+                for _ in 0..n {
+                    block &= block - 1;
+                }
+                let idx = (start + i) * BITS + block.trailing_zeros() as usize;
+                return Some(2 * idx + 1)
+            }
+            n -= count;
+        }
+        unreachable!()
+    }
+
+    /// Return the `n`th prime stored in this sieve, zero-indexed, like
+    /// `nth_prime`.
+    ///
+    /// # Panics
+    ///
+    /// If `n >= self.count_primes()`, that is, if there are not that
+    /// many primes stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.get(0), 2);
+    /// assert_eq!(sieve.get(24), 97);
+    /// ```
+    pub fn get(&self, n: usize) -> usize {
+        match self.nth_prime(n) {
+            Some(p) => p,
+            None => panic!("Primes::get: index {} out of range for {} primes", n, self.count_primes())
+        }
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs.
+    ///
+    /// Returns `Err(FactorError::Zero)` if `n` is zero, and
+    /// `Err(FactorError::LargeCofactor { .. })` with the leftover and
+    /// the partial factorisation if the prime factors of `n` are too
+    /// large for this sieve. This never happens for `n` up to
+    /// `self.upper_bound()^2`.
+    ///
+    /// Trial division stops as soon as what's left of `n` is a prime
+    /// stored in the sieve, so, for example, factorising a large prime
+    /// below the upper bound is just a lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::{Primes, FactorError};
+    ///
+    /// let sieve = Primes::sieve(30);
+    ///
+    /// assert_eq!(sieve.factor(12), Ok(vec![(2, 2), (3, 1)].into()));
+    /// assert_eq!(sieve.factor(0), Err(FactorError::Zero));
+    /// assert_eq!(sieve.factor(2 * 31 * 31),
+    ///            Err(FactorError::LargeCofactor { leftover: 31 * 31,
+    ///                                             partial: vec![(2, 1)].into() }));
+    /// ```
+    pub fn factor(&self, n: usize) -> Result<Factors, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+
+        let mut ret = Vec::new();
+        match self.factor_with(n, |p, k| { ret.push((p, k)); true }) {
+            Ok(()) => Ok(ret.into()),
+            // large factors :(
+            Err(leftover) => Err(FactorError::LargeCofactor { leftover: leftover,
+                                                              partial: ret.into() })
+        }
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs, storing them in
+    /// `out`, which is cleared first.
+    ///
+    /// This is exactly `factor`, but reuses the memory of `out`, to
+    /// avoid allocating a new vector for every number when factorising
+    /// many of them. On `Err(leftover)`, `out` contains the partial
+    /// factorisation, that is, the `partial` of the
+    /// `FactorError::LargeCofactor` that `factor` would return. `n ==
+    /// 0` can't be factorised at all, and gives `Err(0)` with `out`
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use slow_primes::{Primes, Factors};
+    ///
+    /// let sieve = Primes::sieve(30);
+    /// let mut factors = Factors::new();
+    ///
+    /// for n in 1..100 {
+    ///     sieve.factor_into(n, &mut factors).unwrap();
+    ///     assert_eq!(factors.product(), Some(n));
+    /// }
+    ///
+    /// assert_eq!(sieve.factor_into(2 * 31 * 31, &mut factors), Err(31 * 31));
+    /// assert_eq!(factors, [(2, 1)]);
+    /// ```
+    pub fn factor_into(&self, n: usize, out: &mut Factors) -> Result<(), usize> {
+        let pairs = out.pairs_mut();
+        pairs.clear();
+        if n == 0 { return Err(0) }
+
+        self.factor_with(n, |p, k| { pairs.push((p, k)); true })
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs, with `u64`s
+    /// rather than `usize`s, so that every `n` below
+    /// `self.upper_bound()^2` can be factored even where `usize` is
+    /// only 32 bits.
+    ///
+    /// This is the same trial division as `factor`. If the prime
+    /// factors of `n` are too large for this sieve, it returns
+    /// `Err((leftover, partial))`, with the parts that would be in
+    /// `FactorError::LargeCofactor`, and `n == 0` gives `Err((0,
+    /// vec![]))`. The free function `factor_u64` can also split the
+    /// leftover, with Pollard's rho algorithm.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100_000);
+    ///
+    /// // 99_991 is the largest prime in the sieve.
+    /// assert_eq!(sieve.factor_u64(99_991 * 99_991 * 2),
+    ///            Ok(vec![(2, 1), (99_991, 2)]));
+    /// // the largest prime below 99_999^2.
+    /// assert_eq!(sieve.factor_u64(9_999_799_951), Ok(vec![(9_999_799_951, 1)]));
+    /// assert_eq!(sieve.factor_u64(2 * 100_003 * 100_003),
+    ///            Err((100_003 * 100_003, vec![(2, 1)])));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn factor_u64(&self, mut n: u64) -> Result<Vec<(u64, u32)>, (u64, Vec<(u64, u32)>)> {
+        if n == 0 { return Err((0, vec![])) }
+
+        let b = self.upper_bound() as u64;
+        let stored_prime = |n| n <= b && self.is_prime(n as usize);
+        let mut ret = vec![];
+        if stored_prime(n) {
+            return Ok(vec![(n, 1)])
+        }
+        for p in self.primes() {
+            let p = p as u64;
+            // n isn't divisible by anything below p, so if p^2 > n,
+            // it's prime (or 1).
+            if p > n / p { break }
+
+            let mut count = 0;
+            while n % p == 0 {
+                n /= p;
+                count += 1;
+            }
+            if count > 0 {
+                ret.push((p, count));
+                if stored_prime(n) { break }
+            }
+        }
+        if n != 1 {
+            // the square can be larger than a u64.
+            if b as u128 * b as u128 >= n as u128 {
+                ret.push((n, 1))
+            } else {
+                return Err((n, ret))
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Factorise every number in `ns`, returning the same results as
+    /// calling `factor` on each one, in the same order.
+    ///
+    /// Rather than trial dividing each number by the primes separately,
+    /// this walks through the primes only once, dividing every number
+    /// that still has unknown factors by each prime in turn. A number
+    /// is finished as soon as what's left of it is 1, or must be prime
+    /// (when it is less than the square of the current prime), and the
+    /// walk stops once every number is finished.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(30);
+    ///
+    /// let ns = [12, 0, 97, 2 * 31 * 31];
+    /// let expected = ns.iter().map(|&n| sieve.factor(n)).collect::<Vec<_>>();
+    /// assert_eq!(sieve.factor_all(&ns), expected);
+    /// ```
+    pub fn factor_all(&self, ns: &[usize]) -> Vec<Result<Factors, FactorError>> {
+        let mut leftovers = ns.to_vec();
+        let mut factors = vec![vec![]; ns.len()];
+        // the indices of the numbers that haven't been finished.
+        let mut active = (0..ns.len()).filter(|&i| ns[i] > 1).collect::<Vec<_>>();
+
+        for p in self.primes() {
+            if active.is_empty() { break }
+
+            active.retain(|&i| {
+                let n = &mut leftovers[i];
+                let mut count = 0;
+                while *n % p == 0 {
+                    *n /= p;
+                    count += 1;
+                }
+                if count > 0 {
+                    factors[i].push((p, count));
+                }
+                if *n == 1 {
+                    false
+                } else if *n / p < p {
+                    // every prime below p has been divided out, so
+                    // this is prime.
+                    factors[i].push((*n, 1));
+                    *n = 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        // these have run out of primes, as for `factor_with`.
+        let b = self.upper_bound();
+        for i in active {
+            if b * b >= leftovers[i] {
+                factors[i].push((leftovers[i], 1));
+                leftovers[i] = 1;
+            }
+        }
+
+        ns.iter().zip(leftovers).zip(factors).map(|((&n, leftover), factors)| {
+            if n == 0 {
+                Err(FactorError::Zero)
+            } else if leftover == 1 {
+                Ok(factors.into())
+            } else {
+                Err(FactorError::LargeCofactor { leftover: leftover, partial: factors.into() })
+            }
+        }).collect()
+    }
+
+    /// Factorise the non-zero `n` by trial division, calling `f(p,
+    /// k)` for each prime power `p^k` exactly dividing `n`, in order
+    /// of increasing `p`. This stops early if `f` returns `false`.
+    ///
+    /// Returns `Err(leftover)` if `n` cannot be fully factored, as
+    /// for `factor`.
+    pub(crate) fn factor_with<F>(&self, mut n: usize, mut f: F) -> Result<(), usize>
+        where F: FnMut(usize, usize) -> bool
+    {
+        // a prime that is stored doesn't need any (more) trial
+        // division.
+        let stored_prime = |n| n <= self.upper_bound() && self.is_prime(n);
+        if stored_prime(n) {
+            f(n, 1);
+            return Ok(())
+        }
+        for p in self.primes() {
+            if n == 1 { break }
+
+            let mut count = 0;
+            while n % p == 0 {
+                n /= p;
+                count += 1;
+            }
+            if count > 0 {
+                if !f(p, count) {
+                    return Ok(())
+                }
+                if stored_prime(n) {
+                    f(n, 1);
+                    return Ok(())
+                }
+            }
+        }
+        if n != 1 {
+            let b = self.upper_bound();
+            if b * b >= n {
+                // n is not divisible by anything from 1...sqrt(n), so
+                // must be prime itself! (That is, even though we
+                // don't know this prime specifically, we can infer
+                // that it must be prime.)
+                f(n, 1);
+            } else {
+                return Err(n)
+            }
+        }
+        Ok(())
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs, even if it has
+    /// prime factors too large for this sieve.
+    ///
+    /// Small factors are found by trial division, exactly as in
+    /// `factor`, and then any leftover is split with Pollard's rho
+    /// algorithm (Brent's variant), using the deterministic
+    /// Miller-Rabin test to recognise when a factor is prime. The
+    /// result is sorted by prime.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sieve = slow_primes::Primes::sieve(100);
+    ///
+    /// assert_eq!(sieve.factor_large(2 * 1_000_003 * 1_000_003),
+    ///            [(2, 1), (1_000_003, 2)]);
+    /// ```
+    pub fn factor_large(&self, n: usize) -> Factors {
+        let (leftover, ret) = match self.factor(n) {
+            Ok(factors) => return factors,
+            Err(FactorError::Zero) => panic!("Primes::factor_large: cannot factorise zero"),
+            Err(FactorError::LargeCofactor { leftover, partial }) => (leftover, partial)
+        };
+        let mut ret = ret.into_vec();
+
+        // everything left is larger than any prime in the sieve, and
+        // so comes after the factors so far.
+        let mut large = vec![];
+        pollard_rho::split(leftover as u64, &mut large);
+        large.sort();
+        for p in large {
+            let p = p as usize;
+            match ret.last_mut() {
+                Some(&mut (q, ref mut k)) if q == p => {
+                    *k += 1;
+                    continue
+                }
+                _ => {}
+            }
+            ret.push((p, 1))
+        }
+        ret.into()
+    }
+}
+
+/// Sieve up to the largest number in an iterator, so that every one
+/// of them is `contains`ed (and so can be passed to `is_prime`, and
+/// to `factor`).
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::Primes;
+///
+/// let numbers = [12, 97, 1001, 30];
+/// let sieve: Primes = numbers.iter().cloned().collect();
+///
+/// assert!(sieve.upper_bound() >= 1001);
+/// assert_eq!(numbers.iter().filter(|&&n| sieve.is_prime(n)).count(), 1);
+/// ```
+impl iter::FromIterator<usize> for Primes {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Primes {
+        // `sieve` may stop one short of its limit.
+        Primes::sieve(iter.into_iter().max().map_or(0, |n| n.saturating_add(1)))
+    }
+}
+
+/// Two sieves are equal if they store the same numbers, that is, if
+/// they have the same upper bound (and so the same primes).
+///
+/// How a sieve was constructed doesn't matter, only what it stores,
+/// so, for example, `Primes::sieve(29)` and `Primes::sieve(30)` are
+/// equal, since both have upper bound 29. Sieves with different upper
+/// bounds are never equal, even though they agree on the primes they
+/// both store.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::Primes;
+///
+/// assert!(Primes::sieve(29) == Primes::sieve(30));
+/// assert!(Primes::sieve(30) == Primes::sieve_wheel(30));
+/// assert!(Primes::sieve(30) != Primes::sieve(31));
+///
+/// let mut grown = Primes::sieve(30);
+/// grown.grow_to(1000);
+/// assert!(grown == Primes::sieve(1000));
+/// ```
+impl PartialEq for Primes {
+    fn eq(&self, other: &Primes) -> bool {
+        // the index is determined by the bits.
+        self.v == other.v
+    }
+}
+
+impl Eq for Primes {}
+
+/// Hash the numbers stored, consistently with `==`.
+impl Hash for Primes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.v.hash(state)
+    }
+}
+
+/// Summarise a sieve by its upper bound and the number of primes it
+/// stores, rather than listing them all.
+///
+/// # Examples
+///
+/// ```rust
+/// let sieve = slow_primes::Primes::sieve(1000);
+/// assert_eq!(format!("{:?}", sieve), "Primes { upper_bound: 999, count: 168 }");
+/// ```
+impl fmt::Debug for Primes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Primes")
+            .field("upper_bound", &self.upper_bound())
+            .field("count", &self.count_primes())
+            .finish()
+    }
+}
+
+/// List the primes stored in a sieve, eliding the middle if there are
+/// more than ten of them.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::Primes;
+///
+/// assert_eq!(Primes::sieve(20).to_string(), "[2, 3, 5, 7, 11, 13, 17, 19]");
+/// assert_eq!(Primes::sieve(1000).to_string(), "[2, 3, 5, 7, 11, ..., 977, 983, 991, 997]");
+/// ```
+impl fmt::Display for Primes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const FIRST: usize = 5;
+        const LAST: usize = 4;
+
+        let count = self.count_primes();
+        let (first, last) = if count <= FIRST + LAST + 1 { (count, 0) } else { (FIRST, LAST) };
+        f.write_str("[")?;
+        for (i, p) in self.primes().take(first).enumerate() {
+            if i > 0 { f.write_str(", ")? }
+            write!(f, "{}", p)?;
+        }
+        if last > 0 {
+            f.write_str(", ...")?;
+            for p in self.primes().skip(count - last) {
+                write!(f, ", {}", p)?;
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// The number of bytes of heap memory that `Primes::sieve(limit)`
+/// will use, as reported by its `memory_usage`, without allocating
+/// anything.
+///
+/// This allows rejecting a `limit` that is too large before trying
+/// to sieve it. The other constructors use about the same amount.
+///
+/// # Examples
+///
+/// ```rust
+/// use slow_primes::estimated_sieve_memory;
+///
+/// // about 1/16 byte of bits per number, plus the index.
+/// assert_eq!(estimated_sieve_memory(1 << 30), 75_497_488);
+/// assert!(estimated_sieve_memory(usize::MAX) > 1 << 58);
+/// ```
+pub fn estimated_sieve_memory(limit: usize) -> usize {
+    // match `sieve` and `build_index`, computing `(limit + 1) / 2`
+    // without overflowing.
+    let limit = cmp::max(10, limit);
+    let nbits = limit / 2 + limit % 2;
+    let blocks = (nbits + BITS - 1) / BITS;
+    let index = nbits / (BITS * INDEX_STRIDE) + 2;
+    blocks * mem::size_of::<u64>() + index * mem::size_of::<usize>()
+}
+
+// The number of odd numbers stored by a sieve with this upper bound,
+// if it's one `sieve` could have made.
+fn stored_bits(upper_bound: u64) -> Result<usize, &'static str> {
+    // `sieve` never stores fewer than 5 odd numbers.
+    if upper_bound % 2 == 0 || upper_bound < 9 {
+        return Err("invalid upper bound")
+    }
+    if upper_bound >= usize::MAX as u64 {
+        return Err("upper bound too large for this platform")
+    }
+    Ok((upper_bound as usize + 1) / 2)
+}
+
+impl<'a> Iterator for PrimeIterator<'a> {
     type Item = usize;
     #[inline]
     fn next(&mut self) -> Option<usize> {
         if self.two {
             self.two = false;
+            self.remaining -= 1;
             Some(2)
         } else {
-            for (i, is_prime) in &mut self.iter {
-                if is_prime {
+            while self.lo < self.hi {
+                let i = self.lo;
+                self.lo += 1;
+                if self.v[i] {
+                    self.remaining -= 1;
                     return Some(2 * i + 1)
                 }
             }
-            None
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<usize> {
+        if n >= self.remaining {
+            self.two = false;
+            self.lo = self.hi;
+            self.remaining = 0;
+            return None
+        }
+        self.remaining -= n + 1;
+
+        let mut n = n;
+        if self.two {
+            self.two = false;
+            if n == 0 { return Some(2) }
+            n -= 1;
+        }
+
+        // skip whole blocks by counting their bits, only looking at
+        // individual bits in the block containing the one we want.
+        let first = self.lo / BITS;
+        for (i, mut block) in self.v.blocks().enumerate().skip(first) {
+            if i == first {
+                // ignore the bits already visited.
+                block &= !0 << (self.lo % BITS);
+            }
+            let count = block.count_ones() as usize;
+            if n < count {
+                // clear the lowest set bits until the one we want is
+                // the lowest.
+                for _ in 0..n {
+                    block &= block - 1;
+                }
+                let idx = i * BITS + block.trailing_zeros() as usize;
+                self.lo = idx + 1;
+                return Some(2 * idx + 1)
+            }
+            n -= count;
+        }
+        unreachable!("PrimeIterator::nth: fewer primes than expected")
+    }
+
+    #[inline]
+    fn last(mut self) -> Option<usize> {
+        self.next_back()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.remaining
+    }
+
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+        where F: FnMut(B, usize) -> B
+    {
+        let mut acc = init;
+        if self.two {
+            acc = f(acc, 2);
+        }
+        if self.lo >= self.hi {
+            return acc
+        }
+
+        // walk the remaining blocks, only visiting the set bits.
+        let (first, last) = (self.lo / BITS, (self.hi - 1) / BITS);
+        for (i, mut block) in self.v.blocks().enumerate().skip(first).take(last - first + 1) {
+            if i == first {
+                block &= !0 << (self.lo % BITS);
+            }
+            if i == last && self.hi % BITS != 0 {
+                block &= (1 << (self.hi % BITS)) - 1;
+            }
+            while block != 0 {
+                let idx = i * BITS + block.trailing_zeros() as usize;
+                block &= block - 1;
+                acc = f(acc, 2 * idx + 1);
+            }
+        }
+        acc
+    }
+}
+
+impl<'a> DoubleEndedIterator for PrimeIterator<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        while self.lo < self.hi {
+            self.hi -= 1;
+            if self.v[self.hi] {
+                self.remaining -= 1;
+                return Some(2 * self.hi + 1)
+            }
+        }
+        if self.two {
+            self.two = false;
+            self.remaining -= 1;
+            Some(2)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for PrimeIterator<'a> {}
+
+impl<'a> iter::FusedIterator for PrimeIterator<'a> {}
+
+impl<'a> IntoIterator for &'a Primes {
+    type Item = usize;
+    type IntoIter = PrimeIterator<'a>;
+    fn into_iter(self) -> PrimeIterator<'a> {
+        self.primes()
+    }
+}
+
+impl<'a> Iterator for PrimeGaps<'a> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let p = self.lo?;
+        let q = match self.iter.next() {
+            Some(q) => q,
+            None => match self.hi.take() {
+                Some(q) => q,
+                None => {
+                    self.lo = None;
+                    return None
+                }
+            }
+        };
+        self.lo = Some(q);
+        Some((p, q - p))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.lo.is_none() { return (0, Some(0)) }
+
+        // every pair left has a distinct larger prime.
+        let extra = self.hi.is_some() as usize;
+        let (lo, hi) = self.iter.size_hint();
+        (lo + extra, hi.map(|hi| hi + extra))
+    }
+}
+
+impl<'a> DoubleEndedIterator for PrimeGaps<'a> {
+    fn next_back(&mut self) -> Option<(usize, usize)> {
+        let q = self.hi?;
+        let p = match self.iter.next_back() {
+            Some(p) => p,
+            None => match self.lo.take() {
+                Some(p) => p,
+                None => {
+                    self.hi = None;
+                    return None
+                }
+            }
+        };
+        self.hi = Some(p);
+        Some((p, q - p))
+    }
+}
+
+impl<'a> Iterator for TwinPrimeIterator<'a> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        while self.pending == 0 {
+            let block = self.blocks.next()?;
+            // a pair may straddle the edge of the block.
+            let next = self.blocks.peek().map_or(0, |&b| b);
+            self.pending = block & ((block >> 1) | (next << (BITS - 1)));
+            self.base += BITS;
+        }
+
+        let i = self.base - BITS + self.pending.trailing_zeros() as usize;
+        self.pending &= self.pending - 1;
+        let p = 2 * i + 1;
+        Some((p, p + 2))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Primes;
+    use {Factors, FactorError};
+
+    #[test]
+    fn is_prime() {
+        let primes = Primes::sieve(1000);
+        let tests = [
+            (0, false),
+            (1, false),
+            (2, true),
+            (3, true),
+            (4, false),
+            (5, true),
+            (6, false),
+            (7, true),
+            (8, false),
+            (9, false),
+            (10, false),
+            (11, true)
+                ];
+
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.is_prime(n), expected);
+        }
+
+        // the largest stored number needs to be sieved too.
+        for &n in [25, 27, 49, 121, 10_001].iter() {
+            assert!(!Primes::sieve(n).is_prime(n), "{} should be composite", n);
+        }
+    }
+
+    #[test]
+    fn checked_is_prime() {
+        let primes = Primes::sieve(30);
+        for n in 0..primes.upper_bound() + 1 {
+            assert_eq!(primes.checked_is_prime(n), Some(primes.is_prime(n)));
+        }
+        assert_eq!(primes.checked_is_prime(31), None);
+        assert_eq!(primes.checked_is_prime(1001), None);
+        assert_eq!(primes.checked_is_prime(1000), Some(false));
+
+        for &limit in [10, 100, 1000, 1001].iter() {
+            let primes = Primes::sieve(limit);
+            let b = primes.upper_bound();
+            assert_eq!(primes.checked_is_prime(b), Some(primes.is_prime(b)));
+            assert_eq!(primes.checked_is_prime(b + 1), Some(false));
+            assert_eq!(primes.checked_is_prime(b + 2), None);
+        }
+        assert_eq!(primes.checked_is_prime(!0 - 1), Some(false));
+        assert_eq!(primes.checked_is_prime(!0), None);
+    }
+
+    #[test]
+    fn segmented_sieve() {
+        for &limit in [0, 10, 29, 30, 31, 100, 1000, 1001, 10_000, 100_001].iter() {
+            let expected = Primes::sieve(limit);
+            for &seg in [1, 2, 7, 64, 1000, 1 << 20].iter() {
+                let primes = Primes::segmented_sieve(limit, seg);
+                assert!(primes.v == expected.v,
+                        "limit = {}, segment size = {}", limit, seg);
+            }
+        }
+    }
+
+    #[test]
+    fn sieve_wheel() {
+        for limit in (0..2000).chain([10_000, 100_001, 1_000_000, 3_000_017].iter().cloned()) {
+            let expected = Primes::sieve(limit);
+            let primes = Primes::sieve_wheel(limit);
+            assert_eq!(primes.upper_bound(), expected.upper_bound());
+            assert!(primes.primes().eq(expected.primes()), "limit = {}", limit);
+            assert!(primes.v == expected.v, "limit = {}", limit);
+        }
+    }
+
+    #[test]
+    fn grow_to() {
+        let limits = [0, 10, 29, 30, 31, 100, 1000, 1001, 10_000, 100_000];
+        for &from in limits.iter() {
+            for &to in limits.iter() {
+                let mut grown = Primes::sieve(from);
+                grown.grow_to(to);
+
+                let fresh = Primes::sieve(::std::cmp::max(from, to));
+                assert!(grown.v == fresh.v, "growing from {} to {} mismatches", from, to);
+                assert_eq!(grown.upper_bound(), fresh.upper_bound());
+                assert_eq!(grown.counts, fresh.counts, "growing from {} to {}", from, to);
+            }
+        }
+
+        // in lots of small steps, across index entries.
+        let mut grown = Primes::sieve(0);
+        for to in (0..40_000).step_by(97) {
+            grown.grow_to(to);
+            let fresh = Primes::sieve(to);
+            assert!(grown.v == fresh.v, "growing to {} mismatches", to);
+            assert_eq!(grown.counts, fresh.counts, "growing to {}", to);
+        }
+        assert_eq!(grown.prime_pi(30_000), Some(3245));
+        assert_eq!(grown.nth_prime(3000), Some(27_457));
+
+        let mut primes = Primes::sieve(30);
+        assert_eq!(primes.factor(7561),
+                   Err(FactorError::LargeCofactor { leftover: 7561, partial: Factors::new() }));
+        primes.grow_to(100);
+        assert_eq!(primes.factor(7561), Ok(vec![(7561, 1)].into()));
+        assert_eq!(primes.primes().collect::<Vec<_>>(),
+                   Primes::sieve(100).primes().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn eq() {
+        for limit in 0..300 {
+            let sieve = Primes::sieve(limit);
+            assert!(sieve == sieve);
+            assert!(sieve == Primes::sieve_wheel(limit));
+            assert!(sieve == Primes::sieve_atkin(limit));
+            assert!(sieve == Primes::segmented_sieve(limit, 7));
+            for other in [Primes::sieve(limit + 1), Primes::sieve(limit + 2)].iter() {
+                assert_eq!(sieve == *other, sieve.upper_bound() == other.upper_bound(), "{}", limit);
+            }
+        }
+        assert!(Primes::sieve(0) == Primes::sieve(10));
+        assert!(Primes::sieve(11) == Primes::sieve(12));
+        assert!(Primes::sieve(12) != Primes::sieve(13));
+
+        // bits past the upper bound are the only difference.
+        let mut grown = Primes::sieve(100);
+        assert!(grown != Primes::sieve(102));
+        grown.grow_to(102);
+        assert!(grown == Primes::sieve(102));
+    }
+
+    #[test]
+    fn clone_and_hash() {
+        use std::collections::HashMap;
+
+        let sieve = Primes::sieve(1000);
+        let mut grown = sieve.clone();
+        assert_eq!(grown, sieve);
+        grown.grow_to(10_000);
+        assert_eq!(grown, Primes::sieve(10_000));
+        assert_eq!(sieve.upper_bound(), 999);
+        assert_eq!(sieve.count_primes(), 168);
+        assert_eq!(sieve.nth_prime(168), None);
+
+        let mut cache = HashMap::new();
+        for &limit in [10, 100, 1000, 10_000].iter() {
+            cache.insert(Primes::sieve(limit), limit);
+        }
+        assert_eq!(cache.get(&sieve), Some(&1000));
+        assert_eq!(cache.get(&grown), Some(&10_000));
+        // equal sieves are the same key.
+        assert_eq!(cache.get(&Primes::sieve(99)), Some(&100));
+        assert_eq!(cache.get(&Primes::sieve(0)), Some(&10));
+        assert_eq!(cache.get(&Primes::sieve(101)), None);
+        cache.insert(Primes::sieve_wheel(1000), 0);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache[&sieve], 0);
+    }
+
+    #[test]
+    fn debug_and_display() {
+        assert_eq!(format!("{:?}", Primes::sieve(0)), "Primes { upper_bound: 9, count: 4 }");
+        assert_eq!(format!("{:?}", Primes::sieve(1_000_000)),
+                   "Primes { upper_bound: 999999, count: 78498 }");
+        assert_eq!(format!("{:#?}", Primes::sieve(100)),
+                   "Primes {\n    upper_bound: 99,\n    count: 25,\n}");
+
+        assert_eq!(Primes::sieve(0).to_string(), "[2, 3, 5, 7]");
+        // ten primes are all shown, but not eleven.
+        assert_eq!(Primes::sieve(30).to_string(), "[2, 3, 5, 7, 11, 13, 17, 19, 23, 29]");
+        assert_eq!(Primes::sieve(32).to_string(), "[2, 3, 5, 7, 11, ..., 19, 23, 29, 31]");
+        assert_eq!(Primes::sieve(1_000_000).to_string(),
+                   "[2, 3, 5, 7, 11, ..., 999959, 999961, 999979, 999983]");
+    }
+
+    #[test]
+    fn with_at_least() {
+        for n in 0..3000 {
+            let primes = Primes::with_at_least(n);
+            assert!(primes.primes().count() >= n);
+            if n > 0 {
+                assert!(primes.nth_prime(n - 1).is_some());
+            }
+        }
+        let primes = Primes::with_at_least(1_000_000);
+        assert_eq!(primes.nth_prime(999_999), Some(15_485_863));
+    }
+
+    #[test]
+    fn sieve_limit_and_contains() {
+        for &limit in [0, 9, 10, 11, 30, 31, 100, 1000, 1001, 10_000].iter() {
+            let primes = Primes::sieve(limit);
+            let ub = primes.upper_bound();
+            assert_eq!(primes.sieve_limit(), ub + 1);
+            assert!(primes.sieve_limit() >= limit);
+            assert!(primes.sieve_limit() <= ::std::cmp::max(limit, 10) + 1);
+
+            for n in 0..ub + 10 {
+                assert_eq!(primes.contains(n), n <= ub);
+                if primes.contains(n) {
+                    assert_eq!(primes.checked_is_prime(n), Some(primes.is_prime(n)));
+                }
+            }
+            // every prime up to the sieve limit is stored.
+            let big = Primes::sieve(10 * limit + 100);
+            assert!(primes.primes().eq(big.primes().take_while(|&p| p <= primes.sieve_limit())));
+            let square = ub * ub;
+            assert!(primes.factor(square).is_ok());
+        }
+    }
+
+    #[test]
+    fn upper_bound() {
+        let primes = Primes::sieve(30);
+        assert_eq!(primes.upper_bound(), 29);
+        let primes = Primes::sieve(31);
+        assert_eq!(primes.upper_bound(), 31);
+
+        let primes = Primes::sieve(30000);
+        assert_eq!(primes.upper_bound(), 29999);
+        let primes = Primes::sieve(30001);
+        assert_eq!(primes.upper_bound(), 30001);
+    }
+
+    #[test]
+    fn primes_iterator() {
+        let primes = Primes::sieve(50);
+        let mut expected = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
+
+        assert_eq!(primes.primes().collect::<Vec<usize>>(), expected);
+
+        expected.reverse();
+        assert_eq!(primes.primes().rev().collect::<Vec<usize>>(), expected);
+    }
+
+    #[test]
+    fn primes_in_range() {
+        for &(limit, step) in [(10, 1), (30, 1), (31, 1), (100, 1), (1000, 17)].iter() {
+            let primes = Primes::sieve(limit);
+            for low in (0..limit + 10).step_by(step) {
+                for high in (0..limit + 10).step_by(step) {
+                    let expected = primes.primes()
+                        .filter(|&p| low <= p && p < high)
+                        .collect::<Vec<_>>();
+                    let mut range = primes.primes_in_range(low, high);
+                    assert_eq!(range.clone().collect::<Vec<_>>(), expected,
+                               "{}..{} in {}", low, high, limit);
+
+                    let mut backwards = range.clone().rev().collect::<Vec<_>>();
+                    backwards.reverse();
+                    assert_eq!(backwards, expected);
+
+                    // and from both ends at once
+                    let mut both = vec![];
+                    let mut tail = vec![];
+                    while let Some(p) = range.next() {
+                        both.push(p);
+                        match range.next_back() {
+                            Some(p) => tail.push(p),
+                            None => break
+                        }
+                    }
+                    both.extend(tail.into_iter().rev());
+                    assert_eq!(both, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn primes_in() {
+        let primes = Primes::sieve(1_002_000);
+        let ranges = [0..0, 0..2, 0..3, 2..3, 3..3, 1..100, 99..1000,
+                      1_000_000..1_001_000, 1_001_000..2_000_000];
+        for range in ranges.iter() {
+            let expected = primes.primes()
+                .filter(|&p| range.start <= p && p < range.end)
+                .collect::<Vec<_>>();
+
+            let iter = primes.primes_in(range.clone());
+            let (lo, hi) = iter.size_hint();
+            assert!(lo <= expected.len() && expected.len() <= hi.unwrap());
+            assert_eq!(iter.collect::<Vec<_>>(), expected);
+
+            let mut rev = primes.primes_in(range.clone()).rev().collect::<Vec<_>>();
+            rev.reverse();
+            assert_eq!(rev, expected);
+        }
+        assert_eq!(primes.primes_in(1_000_000..1_001_000).count(), 75);
+    }
+
+    #[test]
+    fn prime_pi() {
+        for &limit in [10, 30, 31, 100, 1000, 10000].iter() {
+            let primes = Primes::sieve(limit);
+            for n in 0..primes.upper_bound() + 1 {
+                assert_eq!(primes.prime_pi(n),
+                           Some(primes.primes().take_while(|&p| p <= n).count()));
+            }
+            assert_eq!(primes.prime_pi(primes.upper_bound() + 1), None);
+        }
+
+        let primes = Primes::sieve(1_000_000);
+        assert_eq!(primes.prime_pi(10), Some(4));
+        assert_eq!(primes.prime_pi(100), Some(25));
+        assert_eq!(primes.prime_pi(999_999), Some(78498));
+        assert_eq!(primes.prime_pi(usize::MAX), None);
+
+        // the index is kept up to date when growing.
+        let mut grown = Primes::sieve(100);
+        grown.grow_to(100_000);
+        let fresh = Primes::sieve(100_000);
+        for n in (0..100_000).step_by(7) {
+            assert_eq!(grown.prime_pi(n), fresh.prime_pi(n));
+        }
+    }
+
+    #[test]
+    fn next_prev_prime() {
+        for &limit in [10, 30, 31, 100, 1000, 10000].iter() {
+            let primes = Primes::sieve(limit);
+            let ub = primes.upper_bound();
+            let list = primes.primes().collect::<Vec<_>>();
+            for n in 0..ub + 10 {
+                let next = list.iter().cloned().find(|&p| p > n);
+                assert_eq!(primes.next_prime(n), next, "next_prime({})", n);
+
+                let prev = if n > ub + 1 { None } else { list.iter().cloned().rev().find(|&p| p < n) };
+                assert_eq!(primes.prev_prime(n), prev, "prev_prime({})", n);
+            }
+        }
+
+        let primes = Primes::sieve(100);
+        assert_eq!(primes.next_prime(usize::MAX), None);
+        assert_eq!(primes.next_prime(usize::MAX - 1), None);
+        assert_eq!(primes.prev_prime(usize::MAX), None);
+
+        // a gap longer than a block: 4652353 is followed by the
+        // first gap of 154, which is 77 unset indices.
+        let primes = Primes::sieve(5_000_000);
+        assert_eq!(primes.next_prime(4_652_353), Some(4_652_507));
+        assert_eq!(primes.prev_prime(4_652_507), Some(4_652_353));
+        assert_eq!(primes.prev_prime(4_652_506), Some(4_652_353));
+        assert_eq!(primes.prev_prime(4_652_354), Some(4_652_353));
+    }
+
+    #[test]
+    fn gaps() {
+        for &limit in [10, 30, 31, 100, 1000, 10000].iter() {
+            let primes = Primes::sieve(limit);
+            let list = primes.primes().collect::<Vec<_>>();
+            let expected = list.windows(2).map(|w| (w[0], w[1] - w[0])).collect::<Vec<_>>();
+
+            assert_eq!(primes.gaps().collect::<Vec<_>>(), expected);
+
+            let mut rev = primes.gaps().rev().collect::<Vec<_>>();
+            rev.reverse();
+            assert_eq!(rev, expected);
+
+            let sum = primes.gaps().fold(0, |s, (_, g)| s + g);
+            assert_eq!(sum, list[list.len() - 1] - 2);
+
+            // from both ends, stopping at every possible point
+            for split in 0..expected.len() + 1 {
+                let mut gaps = primes.gaps();
+                let mut front = (&mut gaps).take(split).collect::<Vec<_>>();
+                let (lo, hi) = gaps.size_hint();
+                assert!(lo <= expected.len() - split && expected.len() - split <= hi.unwrap());
+
+                let mut back = gaps.rev().collect::<Vec<_>>();
+                back.reverse();
+                front.extend(back);
+                assert_eq!(front, expected);
+            }
         }
-    }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let mut iter = self.clone();
-        // TODO: this doesn't run in constant time, is it super-bad?
-        match (iter.next(), iter.next_back()) {
-            (Some(lo), Some(hi)) => {
-                let (below_hi, above_hi) = ::estimate_prime_pi(hi as u64);
-                let (below_lo, above_lo) = ::estimate_prime_pi(lo as u64);
+        assert_eq!(Primes::sieve(10).gaps().collect::<Vec<_>>(), [(2, 1), (3, 2), (5, 2)]);
+    }
 
-                ((below_hi - cmp::min(above_lo, below_hi)) as usize,
-                 Some((above_hi - below_lo + 1) as usize))
+    #[test]
+    fn is_prime_unchecked() {
+        for &limit in [10, 100, 1000, 10_000].iter() {
+            let primes = Primes::sieve(limit);
+            for n in 0..primes.upper_bound() + 1 {
+                assert_eq!(unsafe { primes.is_prime_unchecked(n) }, primes.is_prime(n), "{}", n);
             }
-            (Some(_), None) => (1, Some(1)),
-            (None, _) => (0, Some(0))
+            // evens are fine beyond the bound too.
+            assert!(!unsafe { primes.is_prime_unchecked(primes.upper_bound() + 1) });
         }
     }
-}
 
-impl<'a> DoubleEndedIterator for PrimeIterator<'a> {
-    #[inline]
-    fn next_back(&mut self) -> Option<usize> {
-        loop {
-            match self.iter.next_back() {
-                Some((i, true)) => return Some(2 * i + 1),
-                Some((_, false)) => {/* continue */}
-                None if self.two => {
-                    self.two = false;
-                    return Some(2)
-                }
-                None => return None
-            }
+    #[test]
+    fn from_iter() {
+        let empty: Primes = None.into_iter().collect();
+        assert_eq!(empty.upper_bound(), Primes::sieve(0).upper_bound());
+
+        for &max in [0, 1, 9, 10, 11, 100, 101, 1000, 12_345].iter() {
+            let numbers = [3, max, max / 2];
+            let sieve: Primes = numbers.iter().cloned().collect();
+            assert!(numbers.iter().all(|&n| sieve.contains(n)), "{}", max);
+            assert_eq!(sieve.upper_bound(), Primes::sieve(max + 1).upper_bound());
         }
     }
-}
 
+    #[test]
+    fn for_factoring() {
+        for &max in [0, 1, 2, 10, 99, 100, 101, 10_000, 1 << 20, (1 << 40) + 1, 999_999_999_999].iter() {
+            let sieve = Primes::for_factoring(max);
+            let b = sieve.upper_bound();
+            assert!(b * b >= max, "{}", max);
+            assert!(sieve.factor(max.saturating_sub(1) | 1).is_ok());
+        }
+        // 1009^2 needs 1009 itself.
+        assert_eq!(Primes::for_factoring(1009 * 1009).factor(1009 * 1009).unwrap(), [(1009, 2)]);
+    }
 
-#[cfg(test)]
-mod tests {
-    use test::Bencher;
-    use super::Primes;
+    #[test]
+    fn sophie_germain_and_safe_primes() {
+        for &limit in [10, 11, 100, 1000, 10_000].iter() {
+            let primes = Primes::sieve(limit);
+            let ub = primes.upper_bound();
+            let sg = primes.primes()
+                .filter(|&p| 2 * p < ub && primes.is_prime(2 * p + 1))
+                .collect::<Vec<_>>();
+            let safe = primes.primes()
+                .filter(|&q| q > 2 && primes.is_prime((q - 1) / 2))
+                .collect::<Vec<_>>();
+            assert_eq!(primes.sophie_germain_primes().collect::<Vec<_>>(), sg);
+            assert_eq!(primes.safe_primes().collect::<Vec<_>>(), safe);
+        }
+
+        // the partner of 11 is 23, just beyond the sieve.
+        let primes = Primes::sieve(22);
+        assert_eq!(primes.upper_bound(), 21);
+        assert_eq!(primes.sophie_germain_primes().collect::<Vec<_>>(), [2, 3, 5]);
+        assert_eq!(primes.safe_primes().collect::<Vec<_>>(), [5, 7, 11]);
+
+        let primes = Primes::sieve(2_000_001);
+        assert_eq!(primes.sophie_germain_primes().take_while(|&p| p < 1_000_000).count(), 7746);
+        assert_eq!(primes.safe_primes().take_while(|&q| q < 1_000_000).count(), 4324);
+    }
 
     #[test]
-    fn is_prime() {
-        let primes = Primes::sieve(1000);
-        let tests = [
-            (0, false),
-            (1, false),
-            (2, true),
-            (3, true),
-            (4, false),
-            (5, true),
-            (6, false),
-            (7, true),
-            (8, false),
-            (9, false),
-            (10, false),
-            (11, true)
-                ];
+    fn sieve_atkin() {
+        let mut limits = (0..200).collect::<Vec<_>>();
+        limits.extend([1000, 1001, 1024, 9999, 10_000, 65_537, 1_000_000].iter().cloned());
+        for &limit in limits.iter() {
+            let atkin = Primes::sieve_atkin(limit);
+            let sieve = Primes::sieve(limit);
+            assert_eq!(atkin.upper_bound(), sieve.upper_bound(), "{}", limit);
+            assert!(atkin.to_bytes() == sieve.to_bytes(), "{}", limit);
+            assert_eq!(atkin.count_primes(), sieve.count_primes());
+        }
+    }
 
-        for &(n, expected) in tests.iter() {
-            assert_eq!(primes.is_prime(n), expected);
+    #[test]
+    fn prime_powers() {
+        for &limit in [10, 11, 100, 1000, 10_000, 32_768, 100_000].iter() {
+            let primes = Primes::sieve(limit);
+            let ub = primes.upper_bound();
+            let expected = (2..ub + 1)
+                .filter_map(|n| {
+                    let factors = primes.factor(n).unwrap();
+                    if factors.len() == 1 {
+                        Some((n, factors[0].0, factors[0].1 as u32))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(primes.prime_powers().collect::<Vec<_>>(), expected, "{}", limit);
         }
     }
 
     #[test]
-    fn upper_bound() {
-        let primes = Primes::sieve(30);
-        assert_eq!(primes.upper_bound(), 29);
-        let primes = Primes::sieve(31);
-        assert_eq!(primes.upper_bound(), 31);
+    fn prime_gaps() {
+        for &limit in [10, 30, 31, 100, 1000, 10000, 100_000].iter() {
+            let primes = Primes::sieve(limit);
+            let list = primes.primes().collect::<Vec<_>>();
+            let expected = list.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+            assert_eq!(primes.prime_gaps().collect::<Vec<_>>(), expected);
 
-        let primes = Primes::sieve(30000);
-        assert_eq!(primes.upper_bound(), 29999);
-        let primes = Primes::sieve(30001);
-        assert_eq!(primes.upper_bound(), 30001);
+            let max = *expected.iter().max().unwrap();
+            let i = expected.iter().position(|&g| g == max).unwrap();
+            assert_eq!(primes.max_prime_gap(), Some((list[i], list[i + 1])));
+        }
+
+        assert_eq!(Primes::sieve(30).max_prime_gap(), Some((23, 29)));
+        // 7, 11 is the first gap of 4, ahead of 13, 17 and 19, 23.
+        assert_eq!(Primes::sieve(20).max_prime_gap(), Some((7, 11)));
+        assert_eq!(Primes::sieve(100_000).max_prime_gap(), Some((31397, 31469)));
     }
 
     #[test]
-    fn primes_iterator() {
-        let primes = Primes::sieve(50);
-        let mut expected = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
+    fn twin_primes() {
+        for &limit in [10, 30, 31, 62, 63, 64, 65, 100, 1000, 10000].iter() {
+            let primes = Primes::sieve(limit);
+            let expected = primes.primes()
+                .filter(|&p| p + 2 <= primes.upper_bound() && primes.is_prime(p + 2))
+                .map(|p| (p, p + 2))
+                .collect::<Vec<_>>();
+            assert_eq!(primes.twin_primes().collect::<Vec<_>>(), expected);
+        }
 
-        assert_eq!(primes.primes().collect::<Vec<usize>>(), expected);
+        let primes = Primes::sieve(1000);
+        assert_eq!(primes.twin_primes().take(3).collect::<Vec<_>>(),
+                   [(3, 5), (5, 7), (11, 13)]);
+        // (191, 193) straddles the third and fourth blocks.
+        assert!(primes.twin_primes().any(|x| x == (191, 193)));
 
-        expected.reverse();
-        assert_eq!(primes.primes().rev().collect::<Vec<usize>>(), expected);
+        assert_eq!(Primes::sieve(200).twin_primes().collect::<Vec<_>>(),
+                   [(3, 5), (5, 7), (11, 13), (17, 19), (29, 31), (41, 43), (59, 61),
+                    (71, 73), (101, 103), (107, 109), (137, 139), (149, 151),
+                    (179, 181), (191, 193), (197, 199)]);
+        assert_eq!(Primes::sieve(1_000_000).twin_primes().count(), 8169);
+        // stops cleanly when only the smaller of a pair is stored.
+        assert_eq!(Primes::sieve(198).twin_primes().last(), Some((191, 193)));
+    }
+
+    #[test]
+    fn nth_prime() {
+        for &limit in [10, 30, 31, 100, 1000, 10000, 1 << 16, 100_000].iter() {
+            let primes = Primes::sieve(limit);
+            let count = primes.primes().count();
+            for n in 0..count + 10 {
+                assert_eq!(primes.nth_prime(n), primes.primes().nth(n));
+            }
+            for (n, p) in primes.primes().enumerate() {
+                assert_eq!(primes.get(n), p);
+            }
+        }
+
+        let primes = Primes::sieve(10);
+        assert_eq!(primes.nth_prime(0), Some(2));
+        assert_eq!(primes.nth_prime(3), Some(7));
+        assert_eq!(primes.nth_prime(100), None);
+
+        let primes = Primes::sieve(1_000_000);
+        assert_eq!(primes.nth_prime(1), Some(3));
+        assert_eq!(primes.nth_prime(9999), Some(104729));
+        // the last prime stored, and one past it.
+        assert_eq!(primes.nth_prime(78497), Some(999983));
+        assert_eq!(primes.nth_prime(78498), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_out_of_range() {
+        let primes = Primes::sieve(100);
+        primes.get(primes.count_primes());
     }
 
     #[test]
@@ -265,14 +2339,110 @@ mod tests {
 
             (2*2*2*2*2 * 3*3*3*3*3, &[(2, 5), (3,5)]),
             (2*3*5*7*11*13*17*19, &[(2,1), (3,1), (5,1), (7,1), (11,1), (13,1), (17,1), (19,1)]),
+            // primes stored in the map, including the largest
+            (991, &[(991, 1)]),
+            (997, &[(997, 1)]),
+            (2 * 997, &[(2, 1), (997, 1)]),
+            (997 * 997, &[(997, 2)]),
             // a factor larger than that stored in the map
             (7561, &[(7561, 1)]),
             (2*7561, &[(2, 1), (7561, 1)]),
             (4*5*7561, &[(2, 2), (5,1), (7561, 1)]),
             ];
         for &(n, expected) in tests.iter() {
-            assert_eq!(primes.factor(n), Ok(expected.to_vec()));
+            assert_eq!(primes.factor(n), Ok(expected.to_vec().into()));
+
+            let factors = primes.factor(n).unwrap();
+            assert_eq!(factors, expected);
+            assert_eq!(Factors::from(factors.clone().into_vec()), factors);
+        }
+    }
+
+    #[test]
+    fn factor_into() {
+        let short = Primes::sieve(30);
+        let long = Primes::sieve(1000);
+        let mut factors = Factors::new();
+        for n in 0..20_000 {
+            for primes in [&short, &long].iter() {
+                let result = primes.factor_into(n, &mut factors);
+                match primes.factor(n) {
+                    Ok(expected) => {
+                        assert_eq!(result, Ok(()));
+                        assert_eq!(factors, expected);
+                    }
+                    Err(FactorError::Zero) => {
+                        assert_eq!(result, Err(0));
+                        assert!(factors.is_empty());
+                    }
+                    Err(FactorError::LargeCofactor { leftover, partial }) => {
+                        assert_eq!(result, Err(leftover));
+                        assert_eq!(factors, partial);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn factor_u64() {
+        let short = Primes::sieve(30);
+        let long = Primes::sieve(1000);
+        let convert = |f: &[(usize, usize)]| f.iter().map(|&(p, k)| (p as u64, k as u32)).collect::<Vec<_>>();
+        for n in 0..20_000 {
+            for primes in [&short, &long].iter() {
+                let expected = match primes.factor(n) {
+                    Ok(f) => Ok(convert(&f)),
+                    Err(FactorError::Zero) => Err((0, vec![])),
+                    Err(FactorError::LargeCofactor { leftover, partial }) => {
+                        Err((leftover as u64, convert(&partial)))
+                    }
+                };
+                assert_eq!(primes.factor_u64(n as u64), expected, "{}", n);
+            }
+        }
+
+        // above 2^32, with a sieve of about 10^5.
+        let sieve = Primes::sieve(100_000);
+        let b = sieve.primes().last().unwrap() as u64;
+        assert_eq!(b, 99_991);
+        let tests: &[(u64, &[(u64, u32)])] = &[
+            (1 << 40, &[(2, 40)]),
+            (b * b, &[(b, 2)]),
+            (9_999_799_951, &[(9_999_799_951, 1)]),
+            (4_294_967_291 * 2, &[(2, 1), (4_294_967_291, 1)]),
+            (3 * 7 * 99_989 * 99_991, &[(3, 1), (7, 1), (99_989, 1), (99_991, 1)]),
+            (0xFFFF_FFFF_FFFF_FFFF,
+             &[(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]),
+            (97 * 9_999_799_951, &[(97, 1), (9_999_799_951, 1)]),
+            ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(sieve.factor_u64(n), Ok(expected.to_vec()), "{}", n);
+            assert_eq!(::factor_u64(n, &sieve), expected);
         }
+
+        let max = 0xFFFF_FFFF_FFFF_FFFF;
+        assert_eq!(sieve.factor_u64(max - 58), Err((max - 58, vec![])));
+        assert_eq!(sieve.factor_u64(9_999_999_967), Err((9_999_999_967, vec![])));
+        assert_eq!(sieve.factor_u64(4 * 100_003 * 100_019), Err((100_003 * 100_019, vec![(2, 2)])));
+    }
+
+    #[test]
+    fn factor_all() {
+        let short = Primes::sieve(30);
+        let long = Primes::sieve(1000);
+        let ns = (0..20_000).collect::<Vec<_>>();
+        for primes in [&short, &long].iter() {
+            let expected = ns.iter().map(|&n| primes.factor(n)).collect::<Vec<_>>();
+            assert_eq!(primes.factor_all(&ns), expected);
+
+            // unsorted, with repeats, and with numbers too large.
+            let mixed = [7561, 0, 1, 12, 2 * 3 * 31 * 31, 12, 1 << 40, 999_983, 1, 1_000_003 * 1_000_033];
+            let expected = mixed.iter().map(|&n| primes.factor(n)).collect::<Vec<_>>();
+            assert_eq!(primes.factor_all(&mixed), expected);
+        }
+        assert_eq!(long.factor_all(&[]), []);
+        assert_eq!(long.factor_all(&[0, 1]), [Err(FactorError::Zero), Ok(Factors::new())]);
     }
 
     #[test]
@@ -313,9 +2483,10 @@ mod tests {
 
                 // break into the two parts
                 let (low, hi) = real.split_at(last_short_prime);
-                let leftover = hi.iter().fold(1, |x, &(p, i)| x * p.pow(i as u32));
+                let leftover = Factors::from(hi.to_vec()).product().unwrap();
 
-                assert_eq!(possible, Err((leftover, low.to_vec())));
+                assert_eq!(possible, Err(FactorError::LargeCofactor { leftover: leftover,
+                                                                     partial: low.to_vec().into() }));
                 continue 'next_n;
             }
 
@@ -327,20 +2498,90 @@ mod tests {
     #[test]
     fn factor_failures() {
         let primes = Primes::sieve(30);
+        let large = |leftover, partial: &[(usize, usize)]| {
+            Err(FactorError::LargeCofactor { leftover: leftover, partial: partial.to_vec().into() })
+        };
 
-        assert_eq!(primes.factor(0),
-                   Err((0, vec![])));
+        assert_eq!(primes.factor(0), Err(FactorError::Zero));
         // can only handle one large factor
-        assert_eq!(primes.factor(31 * 31),
-                   Err((31 * 31, vec![])));
-        assert_eq!(primes.factor(2 * 3 * 31 * 31),
-                   Err((31 * 31, vec![(2, 1), (3, 1)])));
+        assert_eq!(primes.factor(31 * 31), large(31 * 31, &[]));
+        assert_eq!(primes.factor(2 * 3 * 31 * 31), large(31 * 31, &[(2, 1), (3, 1)]));
 
         // prime that's too large (bigger than 30*30).
-        assert_eq!(primes.factor(7561),
-                   Err((7561, vec![])));
-        assert_eq!(primes.factor(2 * 3 * 7561),
-                   Err((7561, vec![(2, 1), (3, 1)])));
+        assert_eq!(primes.factor(7561), large(7561, &[]));
+        assert_eq!(primes.factor(2 * 3 * 7561), large(7561, &[(2, 1), (3, 1)]));
+
+        // the leftover and partial factorisation multiply back to n,
+        // so factoring can be resumed from there.
+        for n in 1..10_000 {
+            match primes.factor(n) {
+                Ok(_) => {}
+                Err(FactorError::LargeCofactor { leftover, partial }) => {
+                    assert!(leftover > primes.upper_bound() * primes.upper_bound());
+                    assert_eq!(partial.product().unwrap() * leftover, n);
+                }
+                Err(FactorError::Zero) => panic!("zero error for {}", n),
+            }
+        }
+    }
+
+    #[test]
+    fn factor_large() {
+        let primes = Primes::sieve(100);
+
+        let tests: &[(usize, &[(usize, usize)])] = &[
+            (1, &[]),
+            (12, &[(2, 2), (3, 1)]),
+            (7561, &[(7561, 1)]),
+            (101 * 103, &[(101, 1), (103, 1)]),
+            (2 * 3 * 7561 * 7561, &[(2, 1), (3, 1), (7561, 2)]),
+            (1_000_000_007 * 998_244_353, &[(998_244_353, 1), (1_000_000_007, 1)]),
+            (4_294_967_279 * 4_294_967_291, &[(4_294_967_279, 1), (4_294_967_291, 1)]),
+            (3 * 1_000_003 * 1_000_003 * 1_000_033,
+             &[(3, 1), (1_000_003, 2), (1_000_033, 1)]),
+            // strong pseudoprime to all bases up to 23
+            (3_825_123_056_546_413_051, &[(149_491, 1), (747_451, 1), (34_233_211, 1)]),
+            ((1 << 61) - 1, &[((1 << 61) - 1, 1)]),
+            ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(primes.factor_large(n), expected.to_vec());
+        }
+
+        // everything agrees with a sieve that doesn't need any help.
+        let long = Primes::sieve(1000);
+        for n in 1..100_000 {
+            assert_eq!(primes.factor_large(n), long.factor(n).unwrap());
+        }
+    }
+
+    #[test]
+    fn factor_large_failures() {
+        let primes = Primes::sieve(30);
+
+        // everything `factor` can't do...
+        assert_eq!(primes.factor_large(31 * 31), [(31, 2)]);
+        assert_eq!(primes.factor_large(2 * 3 * 31 * 31), [(2, 1), (3, 1), (31, 2)]);
+        assert_eq!(primes.factor_large(7561), [(7561, 1)]);
+        assert_eq!(primes.factor_large(2 * 3 * 7561), [(2, 1), (3, 1), (7561, 1)]);
+
+        // ... including factors just above the bound,
+        assert_eq!(primes.factor_large(31 * 37), [(31, 1), (37, 1)]);
+        assert_eq!(primes.factor_large(4 * 31 * 37 * 41), [(2, 2), (31, 1), (37, 1), (41, 1)]);
+        // and powers of large primes.
+        assert_eq!(primes.factor_large(31 * 31 * 31 * 37), [(31, 3), (37, 1)]);
+        assert_eq!(primes.factor_large(7561 * 7561 * 7561), [(7561, 3)]);
+
+        for n in 1..10_000 {
+            let factors = primes.factor_large(n);
+            assert_eq!(factors.product(), Some(n));
+            assert!(factors.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn factor_large_zero() {
+        Primes::sieve(30).factor_large(0);
     }
 
     #[test]
@@ -350,18 +2591,21 @@ mod tests {
 
             let mut primes = sieve.primes();
 
-            // check the size hint at each and every iteration
+            // check the size hint at each and every iteration, mixing
+            // both ends.
+            let mut step = 0;
             loop {
                 let (lo, hi) = primes.size_hint();
 
                 let copy = primes.clone();
                 let len = copy.count();
 
-                let next = primes.next();
+                let next = if step % 4 == 1 { primes.next_back() } else { primes.next() };
+                step += 1;
 
-                assert!(lo <= len && len <= hi.unwrap(),
-                        "found failing size_hint for {:?} to {}, should satisfy: {} <= {} <= {:?}",
-                        next, i, lo, len, hi);
+                assert!(lo == len && hi == Some(len),
+                        "found failing size_hint for {:?} to {}, should be exactly {}: ({}, {:?})",
+                        next, i, len, lo, hi);
 
                 if next.is_none() {
                     break
@@ -370,6 +2614,189 @@ mod tests {
         }
     }
 
+    #[test]
+    fn nth() {
+        for &limit in [0, 10, 100, 1000, 10_007, 100_000].iter() {
+            let sieve = Primes::sieve(limit);
+            let ranges = [(0, limit + 1), (3, limit / 2), (limit / 3, limit), (50, 70)];
+            for &(lo, hi) in ranges.iter() {
+                let expected = sieve.primes_in_range(lo, hi).collect::<Vec<_>>();
+                for k in 0..expected.len() + 3 {
+                    assert_eq!(sieve.primes_in_range(lo, hi).nth(k), expected.get(k).cloned(),
+                               "{} {}..{} {}", limit, lo, hi, k);
+                }
+                assert_eq!(sieve.primes_in_range(lo, hi).last(), expected.last().cloned());
+
+                // repeated calls, from a partially consumed iterator.
+                let mut iter = sieve.primes_in_range(lo, hi);
+                let mut i = 0;
+                for step in (0..).map(|j| j % 7) {
+                    let next = iter.nth(step);
+                    assert_eq!(next, expected.get(i + step).cloned());
+                    i += step + 1;
+                    assert_eq!(iter.len(), expected.len().saturating_sub(i));
+                    if next.is_none() { break }
+                }
+                assert_eq!(iter.next(), None);
+                assert_eq!(iter.next_back(), None);
+
+                // from the front, after taking some from the back.
+                let mut iter = sieve.primes_in_range(lo, hi);
+                iter.next_back();
+                let len = iter.len();
+                assert_eq!(iter.nth(len), None);
+                let mut iter = sieve.primes_in_range(lo, hi);
+                iter.next_back();
+                if len > 0 {
+                    assert_eq!(iter.nth(len - 1), expected.get(len - 1).cloned());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn count_and_fold() {
+        let sieve = Primes::sieve(10_000);
+        for &(lo, hi) in [(0, 10_001), (0, 3), (2, 3), (3, 3), (63, 129), (100, 5000)].iter() {
+            let expected = sieve.primes_in_range(lo, hi).collect::<Vec<_>>();
+            for front in 0..5 {
+                for back in 0..5 {
+                    let mut iter = sieve.primes_in_range(lo, hi);
+                    let mut remaining = &expected[..];
+                    // alternate the ends, to mix them up.
+                    for i in 0..::std::cmp::max(front, back) {
+                        if i < front && iter.next().is_some() {
+                            remaining = &remaining[1..];
+                        }
+                        if i < back && iter.next_back().is_some() {
+                            remaining = &remaining[..remaining.len() - 1];
+                        }
+                    }
+
+                    assert_eq!(iter.clone().count(), remaining.len());
+                    assert_eq!(iter.clone().fold(vec![], |mut v, p| { v.push(p); v }), remaining);
+                    assert_eq!(iter.clone().sum::<usize>(), remaining.iter().sum::<usize>());
+                    let mut visited = vec![];
+                    iter.for_each(|p| visited.push(p));
+                    assert_eq!(visited, remaining);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn size_hint_rev() {
+        let sieve = Primes::sieve(1000);
+        for &(lo, hi) in [(0, 1000), (3, 500), (900, 2000), (10, 11)].iter() {
+            let mut primes = sieve.primes_in_range(lo, hi).rev();
+            loop {
+                let len = primes.clone().count();
+                assert_eq!(primes.size_hint(), (len, Some(len)));
+                if primes.next().is_none() { break }
+            }
+        }
+    }
+
+    #[test]
+    fn count_primes() {
+        assert_eq!(Primes::sieve(1_000_000).count_primes(), 78498);
+        for &limit in [0, 10, 11, 30, 31, 1000, 1023, 1024, 1025, 100_001].iter() {
+            let mut sieve = Primes::sieve(limit);
+            assert_eq!(sieve.count_primes(), sieve.primes().count());
+            assert_eq!(sieve.primes().len(), sieve.primes().count());
+            assert_eq!(Some(sieve.count_primes()), sieve.prime_pi(sieve.upper_bound()));
+
+            sieve.grow_to(2 * limit + 100);
+            assert_eq!(sieve.count_primes(), sieve.primes().count());
+        }
+
+        // the popcount of the storage, plus 2, if it's included.
+        for limit in 0..300 {
+            let sieve = Primes::sieve(limit);
+            let ones = sieve.v.blocks().map(|b| b.count_ones() as usize).sum::<usize>();
+            let two = if sieve.upper_bound() >= 2 { 1 } else { 0 };
+            assert_eq!(sieve.count_primes(), ones + two, "{}", limit);
+            assert_eq!(sieve.primes().fold(0, |n, _| n + 1), ones + two);
+        }
+    }
+
+    #[test]
+    fn into_iter() {
+        let sieve = Primes::sieve(10_000);
+        let mut primes = sieve.primes();
+        for p in &sieve {
+            assert_eq!(Some(p), primes.next());
+        }
+        assert_eq!(primes.next(), None);
+
+        assert!((&sieve).into_iter().rev().eq(sieve.primes().rev()));
+        assert!(sieve.iter().zip(sieve.primes()).all(|(p, q)| p == q));
+        assert_eq!(sieve.iter().zip(sieve.primes().skip(1)).count(), sieve.count_primes() - 1);
+
+        // it stays finished, from both ends.
+        let small = Primes::sieve(30);
+        let mut iter = small.iter();
+        while iter.next().is_some() {}
+        for _ in 0..3 {
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+        }
+    }
+
+    #[test]
+    fn exact_size() {
+        let sieve = Primes::sieve(10_000);
+        let ranges = [0..0, 0..2, 0..3, 2..3, 3..4, 1..100, 99..1000, 3000..1_000_000, 0..20_000];
+        for range in ranges.iter() {
+            let mut primes = sieve.primes_in(range.clone());
+            let mut len = primes.clone().count();
+            assert_eq!(primes.len(), len);
+
+            // from both ends, in an irregular order
+            let mut i = 0;
+            loop {
+                let next = if i % 3 == 0 { primes.next_back() } else { primes.next() };
+                if next.is_none() { break }
+                len -= 1;
+                assert_eq!(primes.len(), len, "{:?}", range);
+                assert_eq!(primes.size_hint(), (len, Some(len)));
+                i += 1;
+            }
+            assert_eq!(primes.len(), 0);
+        }
+        assert_eq!(sieve.primes().len(), 1229);
+        assert_eq!(sieve.primes().rev().len(), 1229);
+    }
+
+    #[test]
+    fn memory_usage() {
+        use super::estimated_sieve_memory;
+        let word = ::std::mem::size_of::<usize>();
+
+        for &limit in [0, 1, 9, 10, 11, 100, 1023, 1024, 1025, 10_000, 65_537, 1_000_000].iter() {
+            let estimate = estimated_sieve_memory(limit);
+            assert_eq!(Primes::sieve(limit).memory_usage(), estimate, "{}", limit);
+            assert!(estimate >= limit / 16, "{}", limit);
+
+            for other in [Primes::sieve_wheel(limit), Primes::segmented_sieve(limit, 1000)].iter() {
+                let usage = other.memory_usage();
+                assert!(usage + 2 * word >= estimate && usage <= estimate + 2 * word,
+                        "{}: {} vs. {}", limit, usage, estimate);
+            }
+        }
+
+        // growing may leave spare capacity, but never less than needed.
+        let mut grown = Primes::sieve(100);
+        grown.grow_to(100_000);
+        assert!(grown.memory_usage() >= estimated_sieve_memory(100_000));
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
+    use super::Primes;
+
     #[bench]
     fn sieve_small(b: &mut Bencher) {
         b.iter(|| Primes::sieve(100))
@@ -386,6 +2813,37 @@ mod tests {
     fn sieve_huge(b: &mut Bencher) {
         b.iter(|| Primes::sieve(10_000_000))
     }
+    #[bench]
+    fn sieve_wheel_large(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_wheel(100_000))
+    }
+    #[bench]
+    fn sieve_wheel_huge(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_wheel(10_000_000))
+    }
+    #[bench]
+    fn sieve_atkin_large(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_atkin(100_000))
+    }
+    #[bench]
+    fn sieve_atkin_huge(b: &mut Bencher) {
+        b.iter(|| Primes::sieve_atkin(10_000_000))
+    }
+    #[bench]
+    fn segmented_sieve_huge(b: &mut Bencher) {
+        b.iter(|| Primes::segmented_sieve(10_000_000, 1 << 19))
+    }
+
+    #[bench]
+    fn is_prime_large(b: &mut Bencher) {
+        let sieve = Primes::sieve(1_000_000);
+        b.iter(|| (0..1_000_000).filter(|&n| sieve.is_prime(n)).count())
+    }
+    #[bench]
+    fn is_prime_unchecked_large(b: &mut Bencher) {
+        let sieve = Primes::sieve(1_000_000);
+        b.iter(|| (0..1_000_000).filter(|&n| unsafe { sieve.is_prime_unchecked(n) }).count())
+    }
 
     fn bench_iterate(b: &mut Bencher, upto: usize) {
         let sieve = Primes::sieve(upto);
@@ -399,4 +2857,96 @@ mod tests {
     fn iterate_small(b: &mut Bencher) { bench_iterate(b, 100) }
     #[bench]
     fn iterate_large(b: &mut Bencher) { bench_iterate(b, 100_000) }
+
+    #[bench]
+    fn iterate_sum_large(b: &mut Bencher) {
+        let sieve = Primes::sieve(100_000);
+
+        b.iter(|| sieve.primes().sum::<usize>())
+    }
+
+    #[bench]
+    fn prime_pi_large(b: &mut Bencher) {
+        let sieve = Primes::sieve(100_000);
+
+        b.iter(|| sieve.prime_pi(99_999))
+    }
+
+    #[bench]
+    fn iter_nth_millionth(b: &mut Bencher) {
+        let sieve = Primes::sieve(16_000_000);
+
+        b.iter(|| sieve.primes().nth(999_999))
+    }
+    #[bench]
+    fn iter_nth_millionth_naive(b: &mut Bencher) {
+        let sieve = Primes::sieve(16_000_000);
+
+        // the adapter hides the specialised `nth`, so this visits each
+        // bit.
+        b.iter(|| sieve.primes().skip_while(|_| false).nth(999_999))
+    }
+
+    #[bench]
+    fn nth_prime_every(b: &mut Bencher) {
+        let sieve = Primes::sieve(100_000);
+        let count = sieve.count_primes();
+
+        b.iter(|| (0..count).map(|n| sieve.nth_prime(n).unwrap()).sum::<usize>())
+    }
+    #[bench]
+    fn nth_prime_large(b: &mut Bencher) {
+        let sieve = Primes::sieve(100_000);
+
+        b.iter(|| sieve.nth_prime(9000))
+    }
+    #[bench]
+    fn factor_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+
+        b.iter(|| (1..100_000).map(|n| sieve.factor(n).unwrap().len()).sum::<usize>())
+    }
+    #[bench]
+    fn factor_into_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+        let mut factors = ::Factors::new();
+
+        b.iter(|| (1..100_000).map(|n| {
+            sieve.factor_into(n, &mut factors).unwrap();
+            factors.len()
+        }).sum::<usize>())
+    }
+    #[bench]
+    fn factor_all_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+        let ns = (1..5000).map(|n| n * 7919).collect::<Vec<_>>();
+
+        b.iter(|| sieve.factor_all(&ns).len())
+    }
+    #[bench]
+    fn factor_map_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+        let ns = (1..5000).map(|n| n * 7919).collect::<Vec<_>>();
+
+        b.iter(|| ns.iter().map(|&n| sieve.factor(n)).collect::<Vec<_>>().len())
+    }
+    #[bench]
+    fn factor_stored_prime(b: &mut Bencher) {
+        let sieve = Primes::sieve(10_000_000);
+
+        // the largest prime stored.
+        b.iter(|| sieve.factor(9_999_991))
+    }
+    #[bench]
+    fn factor_stored_semiprime(b: &mut Bencher) {
+        let sieve = Primes::sieve(10_000_000);
+
+        b.iter(|| sieve.factor(2 * 4_999_963))
+    }
+    #[bench]
+    fn nth_prime_huge(b: &mut Bencher) {
+        let sieve = Primes::sieve(10_000_000);
+
+        b.iter(|| sieve.nth_prime(600_000))
+    }
 }
@@ -0,0 +1,88 @@
+use smallvec::SmallVec;
+
+use {Primes, FactorError};
+
+/// A factorisation stored inline, without allocating, when there are
+/// at most 8 distinct prime factors, as returned by
+/// `Primes::factor_small`.
+///
+/// Every `n` below 223092870 = 2 * 3 * ... * 23 has at most 8, and
+/// every `u64` has at most 15.
+pub type SmallFactors = SmallVec<[(usize, usize); 8]>;
+
+impl Primes {
+    /// Factorise `n` into (prime, exponent) pairs, without allocating
+    /// for numbers with few distinct prime factors.
+    ///
+    /// This is exactly `factor`, other than the type of the result,
+    /// and it returns the same errors (which do allocate, for the
+    /// partial factorisation).
+    ///
+    /// This requires the `smallvec` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "smallvec")] {
+    /// let sieve = slow_primes::Primes::sieve(30);
+    ///
+    /// let factors = sieve.factor_small(360).unwrap();
+    /// assert_eq!(&factors[..], [(2, 3), (3, 2), (5, 1)]);
+    /// assert!(!factors.spilled());
+    /// # }
+    /// ```
+    pub fn factor_small(&self, n: usize) -> Result<SmallFactors, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+
+        let mut ret = SmallFactors::new();
+        match self.factor_with(n, |p, k| { ret.push((p, k)); true }) {
+            Ok(()) => Ok(ret),
+            Err(leftover) => Err(FactorError::LargeCofactor { leftover: leftover,
+                                                              partial: ret.into_vec().into() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+
+    #[test]
+    fn factor_small() {
+        let sieve = Primes::sieve(1000);
+        for n in 0..100_000 {
+            let small = sieve.factor_small(n).map(|f| f.to_vec());
+            assert_eq!(small, sieve.factor(n).map(|f| f.to_vec()), "{}", n);
+        }
+
+        // the inline storage is just enough for 2 * 3 * ... * 19
+        let primorial = 2 * 3 * 5 * 7 * 11 * 13 * 17 * 19;
+        assert!(!sieve.factor_small(primorial).unwrap().spilled());
+        let spilled = sieve.factor_small(primorial * 23).unwrap();
+        assert!(spilled.spilled());
+        assert_eq!(spilled.len(), 9);
+
+        let small = Primes::sieve(30);
+        let expected = small.factor(2 * 31 * 31).map(|f| f.to_vec().into());
+        assert_eq!(small.factor_small(2 * 31 * 31), expected);
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::{Bencher, black_box};
+    use Primes;
+
+    #[bench]
+    fn factor_small_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+
+        b.iter(|| (1..10_000).filter(|&n| black_box(sieve.factor_small(n)).is_ok()).count())
+    }
+    #[bench]
+    fn factor_vec_range(b: &mut Bencher) {
+        let sieve = Primes::sieve(1000);
+
+        b.iter(|| (1..10_000).filter(|&n| black_box(sieve.factor(n)).is_ok()).count())
+    }
+}
@@ -0,0 +1,159 @@
+use std::cmp;
+use std::vec::Vec;
+
+use {Factors, FactorError};
+
+/// Stores the smallest prime factor of every number up to some
+/// limit, for very fast factorisation of many small numbers.
+///
+/// Factorising `n` takes `O(log n)` divisions, with no trial
+/// division at all, but this needs 4 bytes of storage for every
+/// number up to the limit, 64 times more than `Primes` (which stores
+/// one bit for every odd number).
+///
+/// # Examples
+///
+/// ```rust
+/// let spf = slow_primes::SpfSieve::new(1000);
+///
+/// assert_eq!(spf.smallest_prime_factor(91), Some(7));
+/// assert_eq!(spf.factor(360), Ok(vec![(2, 3), (3, 2), (5, 1)].into()));
+/// ```
+pub struct SpfSieve {
+    // `spf[n]` is the smallest prime factor of `n`, for `n >= 2`.
+    spf: Vec<u32>,
+}
+
+impl SpfSieve {
+    /// Construct a `SpfSieve` storing the smallest prime factors of
+    /// every number up to and including `limit`.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` doesn't fit in a `u32`.
+    pub fn new(limit: usize) -> SpfSieve {
+        assert!(limit as u64 <= u32::MAX as u64,
+                "SpfSieve::new: limit {} is too large", limit);
+
+        let limit = cmp::max(limit, 1);
+        let mut spf = vec![0u32; limit + 1];
+        for i in 2..limit + 1 {
+            if spf[i] != 0 { continue }
+
+            // i is prime.
+            spf[i] = i as u32;
+            let mut m = match i.checked_mul(i) {
+                Some(m) => m,
+                None => continue
+            };
+            while m <= limit {
+                if spf[m] == 0 {
+                    spf[m] = i as u32;
+                }
+                m += i;
+            }
+        }
+        SpfSieve { spf: spf }
+    }
+
+    /// The largest number stored.
+    pub fn limit(&self) -> usize {
+        self.spf.len() - 1
+    }
+
+    /// Return the smallest prime factor of `n`, or `None` if `n` is
+    /// less than 2 or larger than the limit.
+    pub fn smallest_prime_factor(&self, n: usize) -> Option<usize> {
+        if n < 2 || n > self.limit() {
+            None
+        } else {
+            Some(self.spf[n] as usize)
+        }
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs.
+    ///
+    /// Returns `Err(FactorError::Zero)` if `n` is zero, and
+    /// `Err(FactorError::LargeCofactor { .. })` with the whole of `n`
+    /// as the leftover (and an empty partial factorisation) if `n` is
+    /// larger than the limit.
+    pub fn factor(&self, mut n: usize) -> Result<Factors, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+        if n > self.limit() {
+            return Err(FactorError::LargeCofactor { leftover: n, partial: Factors::new() })
+        }
+
+        let mut ret = Vec::new();
+        while n > 1 {
+            let p = self.spf[n] as usize;
+            let mut count = 0;
+            while n % p == 0 {
+                n /= p;
+                count += 1;
+            }
+            ret.push((p, count));
+        }
+        Ok(ret.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Primes, Factors, FactorError};
+    use super::SpfSieve;
+
+    #[test]
+    fn smallest_prime_factor() {
+        let spf = SpfSieve::new(10_000);
+        assert_eq!(spf.limit(), 10_000);
+        assert_eq!(spf.smallest_prime_factor(0), None);
+        assert_eq!(spf.smallest_prime_factor(1), None);
+        assert_eq!(spf.smallest_prime_factor(10_001), None);
+        for n in 2..10_001 {
+            let expected = (2..n + 1).find(|d| n % d == 0);
+            assert_eq!(spf.smallest_prime_factor(n), expected);
+        }
+
+        // tiny limits
+        for &limit in [0, 1, 2, 3, 4].iter() {
+            let spf = SpfSieve::new(limit);
+            assert!(spf.limit() >= limit);
+            for n in 2..limit + 1 {
+                assert_eq!(spf.smallest_prime_factor(n), Some(if n == 4 { 2 } else { n }));
+            }
+        }
+    }
+
+    #[test]
+    fn factor() {
+        let spf = SpfSieve::new(100_000);
+        let primes = Primes::sieve(1000);
+        for n in 1..100_001 {
+            assert_eq!(spf.factor(n), primes.factor(n));
+        }
+
+        assert_eq!(spf.factor(1), Ok(Factors::new()));
+        assert_eq!(spf.factor(0), Err(FactorError::Zero));
+        assert_eq!(spf.factor(100_001),
+                   Err(FactorError::LargeCofactor { leftover: 100_001, partial: Factors::new() }));
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
+    use Primes;
+    use super::SpfSieve;
+
+    const N: usize = 1_000_000;
+    #[bench]
+    fn factor_all_spf(b: &mut Bencher) {
+        let spf = SpfSieve::new(N);
+        b.iter(|| (1..N).map(|n| spf.factor(n).unwrap().len()).sum::<usize>())
+    }
+    #[bench]
+    fn factor_all_trial_division(b: &mut Bencher) {
+        let primes = Primes::sieve(1010);
+        b.iter(|| (1..N).map(|n| primes.factor(n).unwrap().len()).sum::<usize>())
+    }
+}
@@ -0,0 +1,309 @@
+use std::cmp;
+use std::ops::Range;
+use std::vec::Vec;
+
+use {Factors, FactorError};
+use float;
+
+// the numbers less than 30 that are coprime to it, and the gaps to
+// the next one.
+const WHEEL: [usize; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+const GAPS: [usize; 8] = [6, 4, 2, 4, 2, 4, 6, 2];
+// the primes dividing 30.
+const SMALL: [usize; 3] = [2, 3, 5];
+// `INDEX[r]` is the position of `r` in `WHEEL`, or `NONE` if `r`
+// isn't coprime to 30.
+const NONE: u8 = 8;
+const INDEX: [u8; 30] = [
+    NONE, 0, NONE, NONE, NONE, NONE, NONE, 1, NONE, NONE,
+    NONE, 2, NONE, 3, NONE, NONE, NONE, 4, NONE, 5,
+    NONE, NONE, NONE, 6, NONE, NONE, NONE, NONE, NONE, 7,
+];
+
+/// Stores information about primes up to some limit, using a mod-30
+/// wheel.
+///
+/// This answers the same queries as `Primes`, with the same results,
+/// but only stores the numbers coprime to 30 (8 out of every 30), so
+/// it uses about `limit / 30` bytes, rather than `limit / 16`, and
+/// iterating over the primes scans fewer bits.
+///
+/// # Examples
+///
+/// ```rust
+/// let sieve = slow_primes::WheelPrimes::sieve(1000);
+///
+/// assert!(sieve.is_prime(997));
+/// assert_eq!(sieve.primes().take(5).collect::<Vec<_>>(), [2, 3, 5, 7, 11]);
+/// assert_eq!(sieve.factor(360), Ok(vec![(2, 3), (3, 2), (5, 1)].into()));
+/// ```
+pub struct WheelPrimes {
+    // bit `j` of `v[k]` is set if `30 * k + WHEEL[j]` is prime.
+    v: Vec<u8>,
+    upper_bound: usize,
+}
+
+/// Iterator over the primes stored in a `WheelPrimes`.
+#[derive(Clone)]
+pub struct WheelPrimeIterator<'a> {
+    v: &'a [u8],
+    // the indices into `SMALL` that haven't been visited yet.
+    small: Range<usize>,
+    // the bit positions (`8 * k + j`) into `v` that haven't been
+    // visited yet are `lo..hi`.
+    lo: usize,
+    hi: usize,
+}
+
+impl WheelPrimes {
+    /// Construct a `WheelPrimes` via a sieve up to at least `limit`.
+    ///
+    /// This stores exactly the same primes as `Primes::sieve(limit)`,
+    /// and has the same upper bound.
+    pub fn sieve(limit: usize) -> WheelPrimes {
+        // match `Primes::sieve`: the upper bound is the largest odd
+        // number at most `limit`.
+        let limit = cmp::max(10, limit);
+        let upper_bound = (limit - 1) | 1;
+
+        let mut v = vec![0xFF; upper_bound / 30 + 1];
+        // 1 isn't prime
+        v[0] &= !1;
+        // nor is anything past the upper bound.
+        let last = v.len() - 1;
+        for (j, &w) in WHEEL.iter().enumerate() {
+            if 30 * last + w > upper_bound {
+                v[last] &= !(1 << j);
+            }
+        }
+
+        let bound = float::sqrt(upper_bound as f64) as usize + 1;
+        let mut p = 7;
+        let mut i = 1;
+        while p <= bound {
+            if v[p / 30] & (1 << i) != 0 {
+                // cross off p * q for each q >= p coprime to 30.
+                let mut n = p * p;
+                let mut k = i;
+                while n <= upper_bound {
+                    v[n / 30] &= !(1 << INDEX[n % 30]);
+                    n += p * GAPS[k];
+                    k = (k + 1) % 8;
+                }
+            }
+
+            p += GAPS[i];
+            i = (i + 1) % 8;
+        }
+
+        WheelPrimes { v: v, upper_bound: upper_bound }
+    }
+
+    /// The largest number stored.
+    pub fn upper_bound(&self) -> usize {
+        self.upper_bound
+    }
+
+    /// Check if `n` is prime, possibly failing if `n` is larger than
+    /// the upper bound of this instance.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is coprime to 30 and larger than `self.upper_bound()`.
+    /// Use `checked_is_prime` to handle arbitrary inputs.
+    pub fn is_prime(&self, n: usize) -> bool {
+        match self.checked_is_prime(n) {
+            Some(b) => b,
+            None => panic!("WheelPrimes::is_prime: {} is larger than the upper bound {}",
+                           n, self.upper_bound)
+        }
+    }
+
+    /// Check if `n` is prime, returning `None` if `n` is larger than
+    /// the upper bound of this instance.
+    ///
+    /// Numbers sharing a factor with 30 never need the sieve, so this
+    /// always returns `Some` for them.
+    pub fn checked_is_prime(&self, n: usize) -> Option<bool> {
+        let j = INDEX[n % 30];
+        if j == NONE {
+            Some(n == 2 || n == 3 || n == 5)
+        } else if n <= self.upper_bound {
+            Some(self.v[n / 30] & (1 << j) != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Iterator over the primes stored in this map.
+    pub fn primes<'a>(&'a self) -> WheelPrimeIterator<'a> {
+        WheelPrimeIterator {
+            v: &self.v,
+            small: 0..SMALL.len(),
+            lo: 0,
+            hi: 8 * self.v.len(),
+        }
+    }
+
+    /// Factorise `n` into (prime, exponent) pairs.
+    ///
+    /// This behaves exactly like `Primes::factor`.
+    pub fn factor(&self, mut n: usize) -> Result<Factors, FactorError> {
+        if n == 0 { return Err(FactorError::Zero) }
+
+        let mut ret = Vec::new();
+        for p in self.primes() {
+            if n == 1 { break }
+
+            let mut count = 0;
+            while n % p == 0 {
+                n /= p;
+                count += 1;
+            }
+            if count > 0 {
+                ret.push((p, count));
+            }
+        }
+        if n != 1 {
+            let b = self.upper_bound;
+            if b * b >= n {
+                // as for `Primes::factor`, n has no factors up to
+                // sqrt(n), so is prime.
+                ret.push((n, 1));
+            } else {
+                return Err(FactorError::LargeCofactor { leftover: n, partial: ret.into() })
+            }
+        }
+        Ok(ret.into())
+    }
+}
+
+impl<'a> WheelPrimeIterator<'a> {
+    #[inline]
+    fn is_set(&self, i: usize) -> bool {
+        self.v[i / 8] & (1 << (i % 8)) != 0
+    }
+}
+
+#[inline]
+fn number(i: usize) -> usize {
+    30 * (i / 8) + WHEEL[i % 8]
+}
+
+impl<'a> Iterator for WheelPrimeIterator<'a> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if let Some(i) = self.small.next() {
+            return Some(SMALL[i])
+        }
+        while self.lo < self.hi {
+            // skip whole bytes with no primes.
+            if self.lo % 8 == 0 && self.v[self.lo / 8] == 0 && self.lo + 8 <= self.hi {
+                self.lo += 8;
+                continue
+            }
+            let i = self.lo;
+            self.lo += 1;
+            if self.is_set(i) {
+                return Some(number(i))
+            }
+        }
+        None
+    }
+}
+
+impl<'a> DoubleEndedIterator for WheelPrimeIterator<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        while self.lo < self.hi {
+            self.hi -= 1;
+            if self.is_set(self.hi) {
+                return Some(number(self.hi))
+            }
+        }
+        self.small.next_back().map(|i| SMALL[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Primes;
+    use super::WheelPrimes;
+
+    #[test]
+    fn agrees_with_primes() {
+        for limit in (0..300).chain([1000, 1001, 10_000, 100_001, 1_000_000].iter().cloned()) {
+            let expected = Primes::sieve(limit);
+            let wheel = WheelPrimes::sieve(limit);
+            assert_eq!(wheel.upper_bound(), expected.upper_bound());
+
+            for n in 0..wheel.upper_bound() + 1 {
+                assert_eq!(wheel.is_prime(n), expected.is_prime(n), "{} {}", limit, n);
+            }
+            for n in wheel.upper_bound() + 1..wheel.upper_bound() + 100 {
+                // the wheel can answer for more numbers.
+                match expected.checked_is_prime(n) {
+                    Some(b) => assert_eq!(wheel.checked_is_prime(n), Some(b)),
+                    None => assert_eq!(wheel.checked_is_prime(n).is_some(), n % 3 == 0 || n % 5 == 0),
+                }
+            }
+            assert!(wheel.primes().eq(expected.primes()), "{}", limit);
+            assert!(wheel.primes().rev().eq(expected.primes().rev()), "{}", limit);
+        }
+    }
+
+    #[test]
+    fn iterate_both_ends() {
+        let wheel = WheelPrimes::sieve(1000);
+        let expected = Primes::sieve(1000).primes().collect::<Vec<_>>();
+
+        for split in 0..expected.len() + 1 {
+            let mut iter = wheel.primes();
+            let front = iter.by_ref().take(split).collect::<Vec<_>>();
+            let mut back = iter.rev().collect::<Vec<_>>();
+            back.reverse();
+            assert_eq!(front, &expected[..split]);
+            assert_eq!(back, &expected[split..]);
+        }
+    }
+
+    #[test]
+    fn factor() {
+        let wheel = WheelPrimes::sieve(1000);
+        let expected = Primes::sieve(1000);
+        for n in (0..1_000_000).step_by(7).chain(998_000..1_010_000) {
+            assert_eq!(wheel.factor(n), expected.factor(n), "{}", n);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn is_prime_too_large() {
+        WheelPrimes::sieve(1000).is_prime(1001);
+    }
+
+    #[test]
+    fn memory() {
+        let wheel = WheelPrimes::sieve(1_000_000);
+        assert_eq!(wheel.v.len(), 1_000_000 / 30 + 1);
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod benches {
+    use test::Bencher;
+    use super::WheelPrimes;
+
+    #[bench]
+    fn sieve_huge(b: &mut Bencher) {
+        b.iter(|| WheelPrimes::sieve(10_000_000))
+    }
+
+    #[bench]
+    fn iterate_large(b: &mut Bencher) {
+        let sieve = WheelPrimes::sieve(100_000);
+        b.iter(|| sieve.primes().count())
+    }
+}
@@ -0,0 +1,52 @@
+//! Use the library from a `no_std` crate. Run with
+//! `--no-default-features`, the library itself is built without
+//! `std` too, so this checks that the `core` and `alloc` versions of
+//! everything work.
+
+#![no_std]
+
+extern crate alloc;
+extern crate slow_primes;
+
+use alloc::vec::Vec;
+
+use slow_primes::{Primes, Factors, estimate_prime_pi, estimate_nth_prime, is_prime_miller_rabin};
+
+#[test]
+fn sieve() {
+    let sieve = Primes::sieve(1_000_000);
+    assert_eq!(sieve.count_primes(), 78498);
+    assert_eq!(sieve.primes().take(10).collect::<Vec<_>>(), [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    assert_eq!(sieve.primes().last(), Some(999_983));
+    assert_eq!(sieve.nth_prime(9999), Some(104_729));
+    assert!(sieve.is_prime(999_983));
+    assert!(!sieve.is_prime(999_981));
+
+    for n in (1..1_000_000).step_by(999) {
+        assert_eq!(sieve.is_prime(n), is_prime_miller_rabin(n as u64), "{}", n);
+    }
+
+    let mut grown = Primes::sieve(100);
+    grown.grow_to(1_000_000);
+    assert!(grown.primes().eq(sieve.primes()));
+}
+
+#[test]
+fn factor() {
+    let sieve = Primes::sieve(1000);
+    assert_eq!(sieve.factor(360), Ok(Factors::from(alloc::vec![(2, 3), (3, 2), (5, 1)])));
+    assert_eq!(sieve.factor(2 * 991 * 997).unwrap().product(), Some(2 * 991 * 997));
+    assert_eq!(sieve.factor_large(1_000_003 * 1_000_003), [(1_000_003, 2)]);
+}
+
+#[test]
+fn estimates() {
+    // these use the floating point functions from libm without std.
+    let (lo, hi) = estimate_prime_pi(1_000_000);
+    assert!(lo <= 78498 && 78498 <= hi);
+    let (lo, hi) = estimate_nth_prime(10001);
+    assert!(lo <= 104_743 && 104_743 <= hi);
+
+    let sieve = Primes::for_factoring(1_000_000_000_000);
+    assert!(sieve.upper_bound() >= 1_000_000);
+}